@@ -8,10 +8,17 @@ fn main() {
 
 fn set_linker_script() {
 	let cfg_target_arch = env::var("CARGO_CFG_TARGET_ARCH").unwrap();
+	let cfg_target_os = env::var("CARGO_CFG_TARGET_OS").unwrap();
 	let cfg_feature = env::var("CARGO_CFG_FEATURE").unwrap();
+	let has_feature = |feature| cfg_feature.split(',').any(|f| f == feature);
 
 	let linker_script = match cfg_target_arch.as_str() {
-		"x86_64" if cfg_feature.contains("multiboot") => "platform/multiboot/link.ld",
+		"x86_64" if has_feature("multiboot") || has_feature("multiboot2") || has_feature("pvh") => {
+			"platform/multiboot/link.ld"
+		}
+		"aarch64" if has_feature("elf") => "link.ld",
+		"riscv64" if has_feature("sbi") => "link.ld",
+		"riscv64" if cfg_target_os == "uefi" => "uefi.ld",
 		_ => return,
 	};
 
@@ -1,5 +1,4 @@
 use core::num::NonZeroU32;
-use core::ptr;
 
 use fdt::Fdt;
 
@@ -12,16 +11,12 @@ pub struct Console {
 	stdout: SerialPort,
 }
 
-fn stdout() -> SerialPort {
+pub(super) fn stdout() -> SerialPort {
 	/// Physical address of UART0 at Qemu's virt emulation
 	const SERIAL_PORT_ADDRESS: u32 = 0x09000000;
 
-	let fdt = unsafe {
-		Fdt::from_ptr(ptr::with_exposed_provenance(super::DEVICE_TREE as usize))
-			.expect(".fdt file has invalid header")
-	};
-
-	let property = fdt.chosen().stdout();
+	let fdt = device_tree();
+	let property = fdt.as_ref().and_then(|fdt| fdt.chosen().stdout());
 	property
 		.and_then(|node| get_device(node))
 		.unwrap_or(SerialPort::Qemu(QemuSerial::from_addr(
@@ -29,15 +24,30 @@ fn stdout() -> SerialPort {
 		)))
 }
 
+#[cfg(target_os = "none")]
+fn device_tree() -> Option<Fdt<'static>> {
+	Some(super::none::device_tree())
+}
+
+/// Returns the firmware's device tree.
+///
+/// The EFI configuration table only contains a device tree if the firmware does not provide ACPI tables.
+#[cfg(target_os = "uefi")]
+fn device_tree() -> Option<Fdt<'static>> {
+	crate::os::device_tree().map(|dtb| Fdt::new(dtb).unwrap())
+}
+
 impl Console {
 	pub fn write_bytes(&mut self, bytes: &[u8]) {
 		self.stdout.putstr(bytes);
 	}
 
+	#[cfg_attr(target_os = "uefi", expect(dead_code))]
 	pub(super) fn get_stdout(&self) -> u32 {
 		self.stdout.get_addr()
 	}
 
+	#[cfg_attr(target_os = "uefi", expect(dead_code))]
 	pub(crate) fn set_stdout(&mut self, stdout: u32) {
 		match self.stdout {
 			SerialPort::Qemu(_) => {
@@ -48,10 +58,15 @@ impl Console {
 				self.stdout =
 					SerialPort::Xlnx(XlnxSerial::from_addr(NonZeroU32::new(stdout).unwrap()))
 			}
+			SerialPort::Ns16550(ref serial) => {
+				self.stdout =
+					SerialPort::Ns16550(serial.with_addr(NonZeroU32::new(stdout).unwrap()))
+			}
 		}
 		self.stdout.init();
 	}
 
+	#[cfg_attr(target_os = "uefi", expect(dead_code))]
 	pub(crate) fn wait_empty(&mut self) {
 		self.stdout.wait_empty();
 	}
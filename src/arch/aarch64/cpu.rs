@@ -0,0 +1,87 @@
+//! The EL1 configuration that the kernel is entered with.
+//!
+//! This is shared by the ELF entry code and by UEFI, which may run at EL1 or EL2.
+
+use core::arch::global_asm;
+
+use aarch64_cpu::registers::{ID_AA64MMFR0_EL1, MAIR_EL1, Readable, SCTLR_EL1, TCR_EL1};
+use tock_registers::fields::{FieldValue, TryFromValue};
+
+/// Number of virtual address bits for 4KB page
+const VA_BITS: u64 = 48;
+
+global_asm!(include_str!("el2.s"));
+
+unsafe extern "C" {
+	/// Configures EL2 for an AArch64 EL1 guest.
+	///
+	/// This must only be called with `bl` from EL2 and clobbers `x0` and `x1`.
+	pub fn configure_el2();
+}
+
+/// Returns the memory attributes that the page tables refer to.
+pub fn mair_el1() -> FieldValue<u64, MAIR_EL1::Register> {
+	MAIR_EL1::Attr0_Device::nonGathering_nonReordering_noEarlyWriteAck
+		+ MAIR_EL1::Attr1_Device::nonGathering_nonReordering_EarlyWriteAck
+		+ MAIR_EL1::Attr2_Device::Gathering_Reordering_EarlyWriteAck
+		+ MAIR_EL1::Attr3_Normal_Inner::NonCacheable
+		+ MAIR_EL1::Attr3_Normal_Outer::NonCacheable
+		+ MAIR_EL1::Attr4_Normal_Inner::WriteBack_NonTransient_ReadWriteAlloc
+		+ MAIR_EL1::Attr4_Normal_Outer::WriteBack_NonTransient_ReadWriteAlloc
+}
+
+/// Returns the translation control for 48-bit virtual addresses with 4 KiB pages.
+pub fn tcr_el1() -> FieldValue<u64, TCR_EL1::Register> {
+	// determine physical address size
+	let Some(pa_range) = ID_AA64MMFR0_EL1
+		.read_as_enum::<ID_AA64MMFR0_EL1::PARange::Value>(ID_AA64MMFR0_EL1::PARange)
+	else {
+		panic!("Unknown physical address range")
+	};
+	let Some(ips) = TCR_EL1::IPS::Value::try_from_value(pa_range as u64) else {
+		panic!("Invalid physical address size")
+	};
+
+	FieldValue::from(ips)
+		+ TCR_EL1::T1SZ.val(64 - VA_BITS)
+		+ TCR_EL1::T0SZ.val(64 - VA_BITS)
+		+ TCR_EL1::TG1::KiB_4
+		+ TCR_EL1::IRGN0::WriteBack_ReadAlloc_WriteAlloc_Cacheable
+		+ TCR_EL1::IRGN1::WriteBack_ReadAlloc_WriteAlloc_Cacheable
+		+ TCR_EL1::ORGN0::WriteBack_ReadAlloc_WriteAlloc_Cacheable
+		+ TCR_EL1::ORGN1::WriteBack_ReadAlloc_WriteAlloc_Cacheable
+		+ TCR_EL1::SH0::Inner
+		+ TCR_EL1::SH1::Inner
+}
+
+/// Returns the system control without the MMU enable bit.
+pub fn sctlr_el1() -> FieldValue<u64, SCTLR_EL1::Register> {
+	/*
+	* Prepare system control register (SCTRL)
+	* Todo: - Verify if all of these bits actually should be explicitly set
+		   - Link origin of this documentation and check to which instruction set versions
+			 it applies (if applicable)
+		   - Fill in the missing Documentation for some of the bits and verify if we care about them
+			 or if loading and not setting them would be the appropriate action.
+	*/
+
+	#[cfg(target_endian = "big")]
+	let endian = SCTLR_EL1::EE::BigEndian + SCTLR_EL1::E0E::BigEndian;
+	#[cfg(target_endian = "little")]
+	let endian = SCTLR_EL1::EE::LittleEndian + SCTLR_EL1::E0E::LittleEndian;
+
+	SCTLR_EL1::UCI::DontTrap
+		+ SCTLR_EL1::WXN::Disable
+		+ SCTLR_EL1::NTWE::DontTrap
+		+ SCTLR_EL1::NTWI::DontTrap
+		+ SCTLR_EL1::UCT::DontTrap
+		+ SCTLR_EL1::DZE::DontTrap
+		+ SCTLR_EL1::I::Cacheable
+		+ SCTLR_EL1::UMA::Trap
+		+ SCTLR_EL1::NAA::Disable
+		+ SCTLR_EL1::SA0::Enable
+		+ SCTLR_EL1::SA::Enable
+		+ SCTLR_EL1::C::Cacheable
+		+ SCTLR_EL1::A::Disable
+		+ endian
+}
@@ -2,9 +2,12 @@ use core::num::NonZeroU32;
 
 use enum_dispatch::enum_dispatch;
 use fdt::node::FdtNode;
+use log::warn;
+use ns16550_serial::Ns16550Serial;
 use qemu_serial::QemuSerial;
 use xlnx_serial::XlnxSerial;
 
+pub mod ns16550_serial;
 pub mod qemu_serial;
 pub mod xlnx_serial;
 
@@ -26,6 +29,7 @@ pub trait SerialDriver {
 pub enum SerialPort {
 	Qemu(QemuSerial),
 	Xlnx(XlnxSerial),
+	Ns16550(Ns16550Serial),
 }
 
 pub fn get_device<'a>(node: FdtNode<'_, 'a>) -> Option<SerialPort> {
@@ -41,6 +45,27 @@ pub fn get_device<'a>(node: FdtNode<'_, 'a>) -> Option<SerialPort> {
 			return Some(SerialPort::Xlnx(XlnxSerial::from_addr(
 				NonZeroU32::new(reg.starting_address as u32).unwrap(),
 			)));
+		} else if matches!(id, "ns16550a" | "ns16550" | "snps,dw-apb-uart") {
+			let property_u32 = |name, default, supported: &[u32]| {
+				let value = match node.property(name) {
+					Some(property) => property.as_usize(),
+					None => Some(default),
+				};
+				let value = value
+					.and_then(|value| u32::try_from(value).ok())
+					.filter(|value| supported.contains(value));
+				if value.is_none() {
+					warn!("{}: unsupported {name}", node.name);
+				}
+				value
+			};
+			let reg_shift = property_u32("reg-shift", 0, &[0, 1, 2])?;
+			let reg_io_width = property_u32("reg-io-width", 1, &[1, 2, 4])?;
+			return Some(SerialPort::Ns16550(Ns16550Serial::from_addr(
+				NonZeroU32::new(reg.starting_address as u32).unwrap(),
+				reg_shift,
+				reg_io_width,
+			)));
 		}
 	}
 	None
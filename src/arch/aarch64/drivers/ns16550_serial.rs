@@ -0,0 +1,101 @@
+use core::hint;
+use core::num::NonZeroU32;
+use core::ptr::{self, NonNull};
+
+use crate::arch::drivers::SerialSuccess::{ERetry, Success};
+use crate::arch::drivers::{SerialDriver, SerialSuccess};
+
+/// Transmitter Holding Register
+const UART_THR: usize = 0;
+/// Line Status Register
+const UART_LSR: usize = 5;
+
+/// Transmitter Holding Register Empty
+const UART_LSR_THRE: u32 = 1 << 5;
+/// Transmitter Empty
+const UART_LSR_TEMT: u32 = 1 << 6;
+
+/// An MMIO 16550-compatible UART, such as the Synopsys DesignWare APB UART.
+///
+/// The registers are `1 << reg_shift` bytes apart and accessed with `reg_io_width` bytes,
+/// as described by the `reg-shift` and `reg-io-width` device tree properties.
+/// We rely on the firmware for configuring the baud rate and line control.
+pub struct Ns16550Serial {
+	base: NonNull<u8>,
+	reg_shift: u32,
+	reg_io_width: u32,
+}
+
+impl Ns16550Serial {
+	/// Creates the UART, where `reg_io_width` has to be 1, 2, or 4.
+	pub fn from_addr(base_addr: NonZeroU32, reg_shift: u32, reg_io_width: u32) -> Ns16550Serial {
+		Self {
+			base: unsafe { NonNull::new_unchecked(base_addr.get() as *mut u8) },
+			reg_shift,
+			reg_io_width,
+		}
+	}
+
+	/// Returns the same UART at `base_addr`.
+	pub fn with_addr(&self, base_addr: NonZeroU32) -> Ns16550Serial {
+		Self::from_addr(base_addr, self.reg_shift, self.reg_io_width)
+	}
+
+	fn read(&self, reg: usize) -> u32 {
+		let ptr = unsafe { self.base.add(reg << self.reg_shift) };
+		unsafe {
+			match self.reg_io_width {
+				1 => ptr::read_volatile(ptr.as_ptr()).into(),
+				2 => ptr::read_volatile(ptr.cast::<u16>().as_ptr()).into(),
+				_ => ptr::read_volatile(ptr.cast::<u32>().as_ptr()),
+			}
+		}
+	}
+
+	fn write(&mut self, reg: usize, value: u8) {
+		let ptr = unsafe { self.base.add(reg << self.reg_shift) };
+		unsafe {
+			match self.reg_io_width {
+				1 => ptr::write_volatile(ptr.as_ptr(), value),
+				2 => ptr::write_volatile(ptr.cast::<u16>().as_ptr(), value.into()),
+				_ => ptr::write_volatile(ptr.cast::<u32>().as_ptr(), value.into()),
+			}
+		}
+	}
+}
+
+impl SerialDriver for Ns16550Serial {
+	fn init(&mut self) {}
+
+	fn putc(&mut self, c: u8) -> SerialSuccess<u8> {
+		if self.read(UART_LSR) & UART_LSR_THRE == 0 {
+			return ERetry;
+		}
+
+		self.write(UART_THR, c);
+		Success(c)
+	}
+
+	fn putstr(&mut self, s: &[u8]) {
+		for c in s.iter().copied() {
+			if c == b'\n' {
+				while let ERetry = self.putc(b'\r') {
+					hint::spin_loop();
+				}
+			}
+			while let ERetry = self.putc(c) {
+				hint::spin_loop();
+			}
+		}
+	}
+
+	fn get_addr(&self) -> u32 {
+		self.base.as_ptr() as u32
+	}
+
+	fn wait_empty(&mut self) {
+		while self.read(UART_LSR) & UART_LSR_TEMT == 0 {
+			hint::spin_loop();
+		}
+	}
+}
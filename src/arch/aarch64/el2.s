@@ -0,0 +1,42 @@
+// Configure EL2 for an AArch64 EL1 guest.
+// This only clobbers x0 and x1.
+.section .text
+.balign	4
+.global	configure_el2
+configure_el2:
+	// Run EL1 in AArch64 without VHE. This must come first,
+	// since EL1 register accesses are redirected to EL2 with HCR_EL2.E2H set.
+	mov	x0, #(1 << 31)  // RW
+	msr	HCR_EL2, x0
+	isb
+
+	// Let EL1 access the physical counter and timer, and do not offset the virtual counter.
+	mrs	x0, CNTHCTL_EL2
+	orr	x0, x0, #0b11  // EL1PCEN, EL1PCTEN
+	msr	CNTHCTL_EL2, x0
+	msr	CNTVOFF_EL2, xzr
+
+	// Do not trap FP/SIMD or AArch32 CP15 accesses to EL2.
+	mov	x0, #0x33ff
+	msr	CPTR_EL2, x0
+	msr	HSTR_EL2, xzr
+
+	// Report the actual CPU to EL1 and disable stage 2 translation.
+	mrs	x0, MIDR_EL1
+	msr	VPIDR_EL2, x0
+	mrs	x0, MPIDR_EL1
+	msr	VMPIDR_EL2, x0
+	msr	VTTBR_EL2, xzr
+
+	// Let EL1 use the GICv3 system register interface, if implemented.
+	mrs	x0, ID_AA64PFR0_EL1
+	ubfx	x0, x0, #24, #4  // GIC
+	cbz	x0, 1f
+	mrs	x0, ICC_SRE_EL2
+	mov	x1, #0b1001  // Enable, SRE
+	orr	x0, x0, x1
+	msr	ICC_SRE_EL2, x0
+	isb
+	msr	ICH_HCR_EL2, xzr
+1:
+	ret
@@ -1,26 +1,65 @@
 use core::arch::{asm, global_asm};
+use core::ptr;
+use core::sync::atomic::{AtomicPtr, Ordering};
 
 use aarch64_cpu::asm::barrier;
 use aarch64_cpu::registers::{
-	CPACR_EL1, ID_AA64MMFR0_EL1, MAIR_EL1, MDSCR_EL1, ReadWriteable, Readable, SCTLR_EL1, TCR_EL1,
-	TPIDR_EL0, TPIDR_EL1, Writeable,
+	CPACR_EL1, MAIR_EL1, MDSCR_EL1, ReadWriteable, SCTLR_EL1, TCR_EL1, TPIDR_EL0, TPIDR_EL1,
+	Writeable,
 };
 use log::info;
-use tock_registers::fields::{FieldValue, TryFromValue};
 
+use super::cpu;
 use crate::stack::STACK;
 
-/// Number of virtual address bits for 4KB page
-const VA_BITS: u64 = 48;
+/// The flags of the arm64 Image header
+///
+/// The Image uses 4 KiB pages and may be placed anywhere in physical memory.
+/// See <https://docs.kernel.org/arch/arm64/booting.html>.
+const IMAGE_FLAGS: u64 = {
+	const BE: u64 = 1 << 0;
+	const PAGE_SIZE_4K: u64 = 1 << 1;
+	const PHYS_BASE_ANYWHERE: u64 = 1 << 3;
+
+	let endianness = if cfg!(target_endian = "big") { BE } else { 0 };
+	endianness | PAGE_SIZE_4K | PHYS_BASE_ANYWHERE
+};
+
+/// The initial value of `SCTLR_EL1` when dropping from EL2 to EL1
+///
+/// The MMU and caches are off, the RES1 bits of ARMv8.0 are set, and data accesses use our endianness.
+const SCTLR_EL1_INIT: u64 = {
+	const RES1: u64 = 1 << 29 | 1 << 28 | 1 << 23 | 1 << 22 | 1 << 20 | 1 << 11;
+	const EE: u64 = 1 << 25;
+	const E0E: u64 = 1 << 24;
+
+	let endianness = if cfg!(target_endian = "big") {
+		EE | E0E
+	} else {
+		0
+	};
+	RES1 | endianness
+};
+
+/// The device tree pointer passed in `x0` according to the arm64 boot protocol
+static DEVICE_TREE: AtomicPtr<u8> = AtomicPtr::new(ptr::null_mut());
+
+pub fn get_fdt_ptr() -> *mut u8 {
+	DEVICE_TREE.load(Ordering::Relaxed)
+}
 
 global_asm!(
 	include_str!("entry.s"),
+	IMAGE_FLAGS = const IMAGE_FLAGS,
+	SCTLR_EL1_INIT = const SCTLR_EL1_INIT,
 	BOOT_STACK = sym STACK,
 	start_rust = sym start_rust,
+	configure_el2 = sym cpu::configure_el2,
 );
 
 #[inline(never)]
-pub unsafe fn start_rust() -> ! {
+pub unsafe extern "C" fn start_rust(fdt: *mut u8) -> ! {
+	DEVICE_TREE.store(fdt, Ordering::Relaxed);
 	unsafe { pre_init() }
 }
 
@@ -60,42 +99,12 @@ unsafe fn pre_init() -> ! {
 	/*
 	 * Setup memory attribute type tables
 	 */
-	MAIR_EL1.write(
-		MAIR_EL1::Attr0_Device::nonGathering_nonReordering_noEarlyWriteAck
-			+ MAIR_EL1::Attr1_Device::nonGathering_nonReordering_EarlyWriteAck
-			+ MAIR_EL1::Attr2_Device::Gathering_Reordering_EarlyWriteAck
-			+ MAIR_EL1::Attr3_Normal_Inner::NonCacheable
-			+ MAIR_EL1::Attr3_Normal_Outer::NonCacheable
-			+ MAIR_EL1::Attr4_Normal_Inner::WriteBack_NonTransient_ReadWriteAlloc
-			+ MAIR_EL1::Attr4_Normal_Outer::WriteBack_NonTransient_ReadWriteAlloc,
-	);
+	MAIR_EL1.write(cpu::mair_el1());
 
 	/*
 	 * Setup translation control register (TCR)
 	 */
-
-	// determine physical address size
-	let Some(pa_range) = ID_AA64MMFR0_EL1
-		.read_as_enum::<ID_AA64MMFR0_EL1::PARange::Value>(ID_AA64MMFR0_EL1::PARange)
-	else {
-		panic!("Unknown physical address range")
-	};
-	let Some(ips) = TCR_EL1::IPS::Value::try_from_value(pa_range as u64) else {
-		panic!("Invalid physical address size")
-	};
-
-	TCR_EL1.write(
-		FieldValue::from(ips)
-			+ TCR_EL1::T1SZ.val(64 - VA_BITS)
-			+ TCR_EL1::T0SZ.val(64 - VA_BITS)
-			+ TCR_EL1::TG1::KiB_4
-			+ TCR_EL1::IRGN0::WriteBack_ReadAlloc_WriteAlloc_Cacheable
-			+ TCR_EL1::IRGN1::WriteBack_ReadAlloc_WriteAlloc_Cacheable
-			+ TCR_EL1::ORGN0::WriteBack_ReadAlloc_WriteAlloc_Cacheable
-			+ TCR_EL1::ORGN1::WriteBack_ReadAlloc_WriteAlloc_Cacheable
-			+ TCR_EL1::SH0::Inner
-			+ TCR_EL1::SH1::Inner,
-	);
+	TCR_EL1.write(cpu::tcr_el1());
 
 	/*
 	 * Enable FP/ASIMD in Architectural Feature Access Control Register,
@@ -110,37 +119,7 @@ unsafe fn pre_init() -> ! {
 	/* Memory barrier */
 	barrier::dsb(barrier::SY);
 
-	/*
-	* Prepare system control register (SCTRL)
-	* Todo: - Verify if all of these bits actually should be explicitly set
-		   - Link origin of this documentation and check to which instruction set versions
-			 it applies (if applicable)
-		   - Fill in the missing Documentation for some of the bits and verify if we care about them
-			 or if loading and not setting them would be the appropriate action.
-	*/
-
-	#[cfg(target_endian = "big")]
-	let endian = SCTLR_EL1::EE::BigEndian + SCTLR_EL1::E0E::BigEndian;
-	#[cfg(target_endian = "little")]
-	let endian = SCTLR_EL1::EE::LittleEndian + SCTLR_EL1::E0E::LittleEndian;
-
-	SCTLR_EL1.write(
-		SCTLR_EL1::UCI::DontTrap
-			+ SCTLR_EL1::WXN::Disable
-			+ SCTLR_EL1::NTWE::DontTrap
-			+ SCTLR_EL1::NTWI::DontTrap
-			+ SCTLR_EL1::UCT::DontTrap
-			+ SCTLR_EL1::DZE::DontTrap
-			+ SCTLR_EL1::I::Cacheable
-			+ SCTLR_EL1::UMA::Trap
-			+ SCTLR_EL1::NAA::Disable
-			+ SCTLR_EL1::SA0::Enable
-			+ SCTLR_EL1::SA::Enable
-			+ SCTLR_EL1::C::Cacheable
-			+ SCTLR_EL1::A::Disable
-			+ SCTLR_EL1::M::Disable
-			+ endian,
-	);
+	SCTLR_EL1.write(cpu::sctlr_el1() + SCTLR_EL1::M::Disable);
 
 	// Enter loader
 	unsafe {
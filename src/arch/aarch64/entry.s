@@ -2,17 +2,70 @@
 
 .equ _core_id_mask, 0xff
 
-.section .text
+.equ _r_aarch64_relative, 1027
+
+.section .text.head, "ax"
 
+// The arm64 Linux Image header
+// See https://docs.kernel.org/arch/arm64/booting.html
 _start:
+	b	primary_entry  // code0
+	.long	0  // code1
+	.quad	0  // text_offset
+	.quad	__image_size  // image_size
+	.quad	{IMAGE_FLAGS}  // flags
+	.quad	0  // res2
+	.quad	0  // res3
+	.quad	0  // res4
+	.ascii	"ARM\x64"  // magic
+	.long	0  // res5
+
+.section .text
+
+primary_entry:
+	// Preserve the device tree pointer passed by the firmware.
+	mov	x20, x0
+
 	// Only proceed on the boot core. Park it otherwise.
 	mrs	x1, mpidr_el1
 	and	x1, x1, _core_id_mask
 	mov	x2, xzr  // Assume CPU 0 is responsible for booting
 	cmp	x1, x2
-	b.ne	1f
-
-	// If execution reaches here, it is the boot core. Now, prepare the jump to Rust code.
+	b.ne	park
+
+	// If execution reaches here, it is the boot core.
+
+	// Bootloaders do not clear the BSS when loading an Image.
+	adrp	x0, __bss_start
+	add		x0, x0, #:lo12:__bss_start
+	adrp	x1, _end
+	add		x1, x1, #:lo12:_end
+2:	cmp	x0, x1
+	b.hs	3f
+	str	xzr, [x0], #8
+	b	2b
+3:
+
+	// We may run from anywhere, so relocate ourselves by the offset from our link address.
+	adr	x0, _start
+	ldr	x1, =__link_address
+	sub	x0, x0, x1
+	adrp	x1, __rela_start
+	add		x1, x1, #:lo12:__rela_start
+	adrp	x2, __rela_end
+	add		x2, x2, #:lo12:__rela_end
+4:	cmp	x1, x2
+	b.hs	5f
+	ldp	x3, x4, [x1], #16  // r_offset, r_info
+	ldr	x5, [x1], #8  // r_addend
+	cmp	w4, _r_aarch64_relative
+	b.ne	4b
+	add	x5, x5, x0
+	str	x5, [x3, x0]
+	b	4b
+5:
+
+	// Now, prepare the jump to Rust code.
 
 	// This loads the physical address of the stack end. For details see
 	// https://github.com/rust-embedded/rust-raspberrypi-OS-tutorials/blob/master/16_virtual_mem_part4_higher_half_kernel/src/bsp/raspberrypi/link.ld
@@ -53,12 +106,16 @@ _start:
 	eret
 
 // EL2
+// Configure EL2 for an AArch64 EL1 guest and drop to EL1.
 el_2_entry:
+	bl	{configure_el2}
+
+	// Set up EL1 with the MMU off, our vector table, and our stack.
+	ldr	x0, ={SCTLR_EL1_INIT}
+	msr	SCTLR_EL1, x0
+	adr	x0, el_1_vectors
+	msr	VBAR_EL1, x0
 	msr	SP_EL1, x4
-	msr	SCTLR_EL1, xzr
-	mrs	x0, HCR_EL2
-	orr	x0, x0, #(1<<31)
-	msr	HCR_EL2, x0
 
 	mov	x0, #0b1111000101 // D-Flag, I-FLAG, A-FLAG, F-FLAG, EL1h
 	msr	SPSR_EL2, x0
@@ -70,12 +127,23 @@ el_2_entry:
 
 	// Jump to Rust code.
 el_1_entry:
+	mov	x0, x20
 	b	{start_rust}
 
 	// Infinitely wait for events (aka "park the core").
-1:	wfe
-	b	1b
-
-.size	_start, . - _start
+park:
+	wfe
+	b	park
+
+.size	primary_entry, . - primary_entry
+
+// The EL1 vector table parks the core on any exception until the kernel installs its own.
+.balign	0x800
+el_1_vectors:
+.rept	16
+	.balign	0x80
+	b	park
+.endr
+.type	primary_entry, function
 .type	_start, function
 .global	_start
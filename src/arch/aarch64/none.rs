@@ -0,0 +1,179 @@
+use alloc::vec::Vec;
+use core::ptr;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use align_address::Align;
+use fdt::Fdt;
+use hermit_entry::boot_info::{BootInfo, HardwareInfo, PlatformInfo, SerialPortBase};
+use hermit_entry::elf::LoadedKernel;
+use log::info;
+
+use super::paging::{LargePageSize, PageSize};
+use super::{enter_kernel, entry, page_tables};
+use crate::fdt_ext::{self, FdtExt};
+use crate::os::CONSOLE;
+use crate::{BootInfoExt, stack};
+
+/// start address of the RAM at Qemu's virt emulation
+const RAM_START: u64 = 0x40000000;
+/// Qemu assumes for ELF kernel that the fdt is located at
+/// start of RAM (0x4000_0000)
+/// see <https://qemu.readthedocs.io/en/latest/system/arm/virt.html>
+const QEMU_DEVICE_TREE: u64 = RAM_START;
+
+/// End of the most recent allocation
+static NEXT_FREE: AtomicU64 = AtomicU64::new(0);
+
+/// Returns the start of the next allocation of `memory_size` bytes and the end of the free memory after it.
+fn next_free(memory_size: u64) -> (u64, u64) {
+	let initrd = find_kernel().as_ptr_range();
+	let initrd = (initrd.start.expose_provenance() as u64).align_down(LargePageSize::SIZE as u64)
+		..(initrd.end.expose_provenance() as u64).align_up(LargePageSize::SIZE as u64);
+
+	let fdt = {
+		let start = device_tree_ptr().expose_provenance() as u64;
+		let end = start + device_tree().total_size() as u64;
+		start.align_down(LargePageSize::SIZE as u64)..end.align_up(LargePageSize::SIZE as u64)
+	};
+
+	let start = match NEXT_FREE.load(Ordering::Relaxed) {
+		0 => elf_symbols::executable_end().expose_provenance() as u64,
+		next_free => next_free,
+	};
+
+	// The kernel reads the additional modules after booting.
+	let reserved = [initrd, fdt]
+		.into_iter()
+		.chain(device_tree().module_regions())
+		.collect::<Vec<_>>();
+
+	device_tree()
+		.find_free_memory(start, memory_size, LargePageSize::SIZE as u64, &reserved)
+		.expect("Not enough free memory")
+}
+
+/// Allocates `memory_size` bytes and identity-maps them for when the MMU is enabled.
+pub unsafe fn get_memory(memory_size: u64) -> u64 {
+	let memory_size = memory_size.align_up(LargePageSize::SIZE as u64);
+	let (start, end) = next_free(memory_size);
+	assert!(start + memory_size <= end);
+
+	unsafe {
+		page_tables::map_ram(start..start + memory_size);
+	}
+
+	// We cannot use read-modify-write atomics before the MMU is enabled.
+	NEXT_FREE.store(start + memory_size, Ordering::Relaxed);
+	start
+}
+
+/// Returns the number of bytes of free memory after the next allocation of `memory_size` bytes.
+pub fn free_memory_after(memory_size: u64) -> u64 {
+	let memory_size = memory_size.align_up(LargePageSize::SIZE as u64);
+	let (start, end) = next_free(memory_size);
+	end.saturating_sub(start + memory_size)
+}
+
+/// Returns the device tree passed by the firmware.
+///
+/// If there is no valid device tree in `x0`, we fall back to where QEMU places it for ELF kernels.
+fn device_tree_ptr() -> *mut u8 {
+	let fdt_ptr = entry::get_fdt_ptr();
+	if !fdt_ptr.is_null() && unsafe { Fdt::from_ptr(fdt_ptr) }.is_ok() {
+		fdt_ptr
+	} else {
+		ptr::with_exposed_provenance_mut(QEMU_DEVICE_TREE as usize)
+	}
+}
+
+pub(super) fn device_tree() -> Fdt<'static> {
+	unsafe { Fdt::from_ptr(device_tree_ptr()).expect(".fdt file has invalid header") }
+}
+
+pub fn find_kernel() -> &'static [u8] {
+	device_tree().find_kernel().unwrap()
+}
+
+pub fn command_line() -> Option<&'static str> {
+	device_tree()
+		.find_node("/chosen")?
+		.property("bootargs")?
+		.as_str()
+}
+
+#[allow(static_mut_refs)] // FIXME: disallow
+pub unsafe fn boot_kernel(
+	kernel_info: LoadedKernel,
+	bootargs: Option<&'static str>,
+	app_sha256: [u8; 32],
+) -> ! {
+	let LoadedKernel {
+		load_info,
+		entry_point,
+	} = kernel_info;
+
+	info!("Found device tree at {:p}", device_tree_ptr());
+
+	let device_tree = unsafe { fdt_ext::prepare(device_tree_ptr(), bootargs, app_sha256) };
+
+	let fdt = unsafe { Fdt::from_ptr(device_tree).expect(".fdt file has invalid header") };
+	let cpus = fdt.cpus().count();
+	info!("Detect {cpus} CPU(s)");
+
+	let uart_address: u32 = CONSOLE.lock().get().get_stdout();
+	info!("Detect UART at {uart_address:#x}");
+
+	let loader_start = elf_symbols::executable_start().expose_provenance() as u64;
+	let loader_end = elf_symbols::executable_end().expose_provenance() as u64;
+	// The device tree may be the firmware's, which is not necessarily in allocated memory.
+	let device_tree_start = device_tree.expose_provenance() as u64;
+	let device_tree_end = device_tree_start + fdt.total_size() as u64;
+	unsafe {
+		page_tables::init();
+		page_tables::map_serial(uart_address);
+		page_tables::map_ram(loader_start..loader_end);
+		page_tables::map_ram(device_tree_start..device_tree_end);
+	}
+
+	let serial_address = page_tables::serial_address(uart_address);
+	CONSOLE.lock().get().set_stdout(serial_address as u32);
+
+	unsafe {
+		page_tables::enable();
+	}
+
+	let fdt = unsafe { Fdt::from_ptr(device_tree).expect(".fdt file has invalid header") };
+
+	if let Some(device_type) = fdt
+		.find_node("/memory")
+		.and_then(|node| node.property("device_type"))
+	{
+		let device_type = core::str::from_utf8(device_type.value)
+			.unwrap()
+			.trim_matches(char::from(0));
+		assert!(device_type == "memory");
+	}
+	for region in fdt.memory_regions() {
+		info!("Memory region: {region:#x?}");
+	}
+	fdt.log_additional_modules();
+	let phys_addr_range = fdt.phys_addr_range();
+
+	info!("phys_addr_range: {phys_addr_range:#x?}. Trying to jump into kernel soon.");
+	let boot_info = BootInfo {
+		hardware_info: HardwareInfo {
+			phys_addr_range,
+			serial_port_base: SerialPortBase::new(serial_address as u64),
+			device_tree: core::num::NonZeroU64::new(device_tree.expose_provenance() as u64),
+		},
+		load_info,
+		platform_info: PlatformInfo::LinuxBoot,
+	};
+
+	let stack = stack::get_stack_ptr();
+	let entry = ptr::with_exposed_provenance(entry_point.try_into().unwrap());
+	let raw_boot_info = boot_info.write();
+
+	CONSOLE.lock().get().wait_empty();
+	unsafe { enter_kernel(stack, entry, raw_boot_info) }
+}
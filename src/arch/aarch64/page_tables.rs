@@ -1,103 +1,143 @@
+#[cfg(target_os = "uefi")]
+use core::arch::asm;
+use core::ops::Range;
 use core::ptr;
 
 use aarch64_cpu::asm::barrier::{SY, dsb, isb};
-use aarch64_cpu::registers::{ReadWriteable, SCTLR_EL1, TTBR0_EL1, TTBR1_EL1, Writeable};
+#[cfg(target_os = "none")]
+use aarch64_cpu::registers::ReadWriteable;
+#[cfg(target_os = "uefi")]
+use aarch64_cpu::registers::{CPACR_EL1, CurrentEL, MAIR_EL1, MDSCR_EL1, Readable, TCR_EL1};
+use aarch64_cpu::registers::{SCTLR_EL1, TTBR0_EL1, TTBR1_EL1, Writeable};
+use align_address::Align;
+#[cfg(target_os = "none")]
 use log::info;
 
-use super::RAM_START;
-use super::paging::{BasePageSize, PageSize};
-
-static mut LEVEL_0_TABLE: PageTable = {
-	let mut table = [ptr::null_mut(); _];
-
-	table[0] = (&raw mut LEVEL_1_TABLE)
-		.wrapping_byte_add(descr::NORMAL)
-		.cast();
-	table[511] = (&raw mut LEVEL_0_TABLE)
-		.wrapping_byte_add(descr::NORMAL)
-		.wrapping_byte_add(descr::SELF)
-		.cast();
-
-	PageTable(table)
+#[cfg(target_os = "uefi")]
+use super::cpu;
+use super::paging::{
+	BasePageSize, LargePageSize, PAGE_BITS, PAGE_MAP_BITS, PAGE_MAP_MASK, PageSize,
 };
 
-static mut LEVEL_1_TABLE: PageTable = {
-	let mut table = [ptr::null_mut(); _];
-
-	table[0] = (&raw mut LEVEL_2_TABLE_SERIAL)
-		.wrapping_byte_add(descr::NORMAL)
-		.cast();
-	table[1] = (&raw mut LEVEL_2_TABLE_RAM)
-		.wrapping_byte_add(descr::NORMAL)
-		.cast();
-
-	PageTable(table)
-};
+/// The virtual address of the page containing the serial port.
+const SERIAL_ADDRESS: usize = 0x1000;
 
-static mut LEVEL_2_TABLE_SERIAL: PageTable = {
-	let mut table = [ptr::null_mut(); _];
-
-	table[0] = (&raw mut LEVEL_3_TABLE_SERIAL)
-		.wrapping_byte_add(descr::NORMAL)
-		.cast();
-
-	PageTable(table)
-};
+/// The number of page tables
+///
+/// RAM is identity-mapped in 1 GiB and 2 MiB blocks, which needs one level 1 table per 512 GiB
+/// and one level 2 table per partially mapped GiB.
+/// The serial port needs one level 1, level 2, and level 3 table of its own.
+const PAGE_TABLE_COUNT: usize = 16;
 
-static mut LEVEL_2_TABLE_RAM: PageTable = {
-	let mut table = [ptr::null_mut(); _];
+/// The page tables, starting with the level 0 table.
+static mut PAGE_TABLES: [PageTable; PAGE_TABLE_COUNT] = [PageTable([ptr::null_mut(); _]); _];
 
-	let mut entry_i = 0;
-	while entry_i < 10usize {
-		let entry = &mut table[entry_i];
+/// The number of used tables in [`PAGE_TABLES`]
+static mut USED_PAGE_TABLES: usize = 1;
 
-		let level_3_table = unsafe { &raw mut LEVEL_3_TABLES_RAM[entry_i] };
-		*entry = level_3_table.wrapping_byte_add(descr::NORMAL).cast();
+/// Returns the virtual address of the serial port at `uart_address`.
+///
+/// UARTs such as 16550s are not necessarily page-aligned.
+pub fn serial_address(uart_address: u32) -> usize {
+	SERIAL_ADDRESS + uart_address as usize % BasePageSize::SIZE
+}
 
-		entry_i += 1;
+pub unsafe fn init() {
+	unsafe {
+		let level_0_table = &raw mut PAGE_TABLES[0];
+		(*level_0_table).0[511] = level_0_table
+			.wrapping_byte_add(descr::NORMAL)
+			.wrapping_byte_add(descr::SELF)
+			.cast();
 	}
+}
 
-	PageTable(table)
-};
-
-static mut LEVEL_3_TABLE_SERIAL: PageTable = PageTable([ptr::null_mut(); _]);
-
-static mut LEVEL_3_TABLES_RAM: [PageTable; 10] = {
-	let mut tables = [PageTable([ptr::null_mut(); _]); _];
-
-	let mut table_i = 0;
-	while table_i < tables.len() {
-		let table = &mut tables[table_i].0;
-
-		let mut entry_i = 0;
-		while entry_i < table.len() {
-			let entry = &mut table[entry_i];
-
-			let addr = (table_i * 512 + entry_i) * BasePageSize::SIZE;
-			*entry = ptr::with_exposed_provenance_mut::<()>(RAM_START as usize)
-				.wrapping_byte_add(addr)
-				.wrapping_byte_add(descr::NORMAL);
+/// Maps the serial port at `uart_address` to [`serial_address`].
+///
+/// This must be called before any RAM is mapped.
+pub unsafe fn map_serial(uart_address: u32) {
+	unsafe {
+		let serial_entry = entry(SERIAL_ADDRESS, 3).expect("The serial port is mapped by a block");
+		*serial_entry = ptr::with_exposed_provenance_mut::<()>(
+			(uart_address as usize).align_down(BasePageSize::SIZE),
+		)
+		.wrapping_byte_add(descr::NON_CACHEABLE);
+	}
+}
 
-			entry_i += 1;
+/// Identity-maps the RAM in `memory`, rounded to 2 MiB blocks.
+///
+/// Whole gigabytes are mapped with 1 GiB blocks to save tables.
+/// This must be called before [`enable`].
+pub unsafe fn map_ram(memory: Range<u64>) {
+	/// The size of a level 1 block
+	const HUGE_PAGE_SIZE: usize = 1 << 30;
+
+	let start = memory.start.align_down(LargePageSize::SIZE as u64) as usize;
+	let end = memory.end.align_up(LargePageSize::SIZE as u64) as usize;
+
+	let mut address = start;
+	while address < end {
+		let block = ptr::with_exposed_provenance_mut::<()>(address).wrapping_byte_add(descr::BLOCK);
+
+		if address.is_multiple_of(HUGE_PAGE_SIZE)
+			&& address + HUGE_PAGE_SIZE <= end
+			&& let Some(entry) = unsafe { entry(address, 1) }
+			&& entry.is_null()
+		{
+			*entry = block;
+			address += HUGE_PAGE_SIZE;
+			continue;
 		}
 
-		table_i += 1;
+		// We never replace existing mappings.
+		if let Some(entry) = unsafe { entry(address, 2) }
+			&& entry.is_null()
+		{
+			*entry = block;
+		}
+		address += LargePageSize::SIZE;
 	}
+}
 
-	tables
-};
-
-pub unsafe fn init(uart_address: u32) {
-	unsafe {
-		LEVEL_3_TABLE_SERIAL.0[1] = ptr::with_exposed_provenance_mut::<()>(uart_address as usize)
-			.wrapping_byte_add(descr::NON_CACHEABLE);
+/// Returns the entry for `virtual_address` in a table of `level`, allocating tables as needed.
+///
+/// Level 1 entries map 1 GiB blocks, level 2 entries map 2 MiB blocks, and level 3 entries map 4 KiB pages.
+/// Returns `None` if `virtual_address` is already mapped by a block of a lower level.
+unsafe fn entry(virtual_address: usize, level: usize) -> Option<&'static mut *mut ()> {
+	/// The bits of a table descriptor that contain the address of the next table
+	const ADDRESS_MASK: usize = 0x0000_ffff_ffff_f000;
+
+	let index = |level: usize| {
+		let shift = PAGE_BITS + PAGE_MAP_BITS * (3 - level);
+		(virtual_address >> shift) & PAGE_MAP_MASK
+	};
+
+	let mut table = unsafe { &raw mut PAGE_TABLES[0] };
+	for level in 0..level {
+		let entry = unsafe { &mut (*table).0[index(level)] };
+		if entry.is_null() {
+			let next = unsafe {
+				assert!(USED_PAGE_TABLES < PAGE_TABLE_COUNT, "Out of page tables");
+				let next = &raw mut PAGE_TABLES[USED_PAGE_TABLES];
+				USED_PAGE_TABLES += 1;
+				next
+			};
+			*entry = next.wrapping_byte_add(descr::NORMAL).cast();
+		} else if entry.addr() & descr::TABLE == 0 {
+			return None;
+		}
+		table = entry.map_addr(|addr| addr & ADDRESS_MASK).cast();
 	}
+
+	Some(unsafe { &mut (*table).0[index(level)] })
 }
 
+#[cfg(target_os = "none")]
 pub unsafe fn enable() {
 	// Set Translation Table Base Registers (TTBR)
 	TTBR1_EL1.set(0);
-	TTBR0_EL1.set((&raw mut LEVEL_0_TABLE).expose_provenance() as u64);
+	TTBR0_EL1.set((&raw mut PAGE_TABLES).expose_provenance() as u64);
 	dsb(SY);
 	isb(SY);
 
@@ -108,6 +148,72 @@ pub unsafe fn enable() {
 	info!("Successfully set up paging.");
 }
 
+/// Switches from the firmware's translation tables to ours.
+///
+/// If the firmware runs at EL2, this drops to EL1 like the ELF entry code.
+/// Both the firmware's and our tables have to identity-map the running code and stack.
+#[cfg(target_os = "uefi")]
+pub unsafe fn switch() {
+	let ttbr0 = (&raw mut PAGE_TABLES).expose_provenance() as u64;
+	let mair = cpu::mair_el1().value;
+	let tcr = cpu::tcr_el1().value;
+
+	// Make our tables visible to the table walker.
+	dsb(SY);
+
+	if CurrentEL.read(CurrentEL::EL) == 2 {
+		// The EL1 translation regime is not in use yet, so it can be set up before entering it.
+		MAIR_EL1.set(mair);
+		TCR_EL1.set(tcr);
+		TTBR0_EL1.set(ttbr0);
+		TTBR1_EL1.set(0);
+		CPACR_EL1.write(CPACR_EL1::FPEN::TrapNothing);
+		MDSCR_EL1.set(0);
+		SCTLR_EL1.write(cpu::sctlr_el1() + SCTLR_EL1::M::Enable);
+
+		unsafe {
+			asm!(
+				"bl {configure_el2}",
+				"tlbi vmalle1",
+				"dsb nsh",
+				"mov x0, sp",
+				"msr SP_EL1, x0",
+				"mov x0, #0b1111000101", // D-Flag, I-FLAG, A-FLAG, F-FLAG, EL1h
+				"msr SPSR_EL2, x0",
+				"adr x0, 2f",
+				"msr ELR_EL2, x0",
+				"eret",
+				"2:",
+				configure_el2 = sym cpu::configure_el2,
+				out("x0") _,
+				out("x1") _,
+				out("x30") _,
+			);
+		}
+	} else {
+		// Our tables and attributes are only consistent after all three have been replaced.
+		unsafe {
+			asm!(
+				"msr MAIR_EL1, {mair}",
+				"msr TCR_EL1, {tcr}",
+				"msr TTBR0_EL1, {ttbr0}",
+				"msr TTBR1_EL1, xzr",
+				"isb",
+				"tlbi vmalle1",
+				"dsb nsh",
+				"isb",
+				mair = in(reg) mair,
+				tcr = in(reg) tcr,
+				ttbr0 = in(reg) ttbr0,
+				options(nostack),
+			);
+		}
+		MDSCR_EL1.set(0);
+		SCTLR_EL1.write(cpu::sctlr_el1() + SCTLR_EL1::M::Enable);
+		isb(SY);
+	}
+}
+
 #[derive(Clone, Copy, Debug)]
 #[repr(C, align(0x1000))]
 struct PageTable([*mut (); 512]);
@@ -118,12 +224,15 @@ struct PageTable([*mut (); 512]);
 mod descr {
 	pub const NORMAL: usize = AF | SH_INNER | attr_indx(4) | TABLE | VALID;
 	pub const NON_CACHEABLE: usize = AF | SH_INNER | attr_indx(3) | TABLE | VALID;
+	pub const BLOCK: usize = AF | SH_INNER | attr_indx(4) | VALID;
 
 	/// Valid descriptor
 	const VALID: usize = 1;
 
-	/// Table descriptor
-	const TABLE: usize = 1 << 1;
+	/// Table or page descriptor
+	///
+	/// Without this bit, level 1 and level 2 descriptors are block descriptors.
+	pub const TABLE: usize = 1 << 1;
 
 	/// Attribute index
 	///
@@ -0,0 +1,35 @@
+use core::ops::Range;
+
+use hermit_entry::boot_info::SerialPortBase;
+
+use super::drivers::SerialDriver;
+use super::{console, page_tables};
+
+/// Returns the address of the serial port that the console uses after exiting boot services.
+pub fn serial_port_address() -> u64 {
+	console::stdout().get_addr().into()
+}
+
+/// Identity-maps `memory` and maps the serial port for entering the kernel.
+///
+/// Returns the address of the serial port in the loader's page tables.
+pub unsafe fn init_page_tables(
+	memory: impl Iterator<Item = Range<u64>>,
+	serial_port_base: Option<SerialPortBase>,
+) -> Option<SerialPortBase> {
+	let uart_address = serial_port_base.map(|base| u32::try_from(base.get()).unwrap());
+
+	unsafe {
+		page_tables::init();
+		if let Some(uart_address) = uart_address {
+			page_tables::map_serial(uart_address);
+		}
+		for memory in memory {
+			page_tables::map_ram(memory);
+		}
+	}
+
+	uart_address
+		.map(|uart_address| page_tables::serial_address(uart_address) as u64)
+		.and_then(SerialPortBase::new)
+}
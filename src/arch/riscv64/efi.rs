@@ -0,0 +1,15 @@
+//! The PE/COFF header and entry point of the UEFI application.
+//!
+//! LLVM cannot emit COFF objects for RISC-V, so we link a position-independent ELF with a handwritten PE/COFF header,
+//! similar to Linux's EFI stub, and convert it to a raw binary.
+//! Since the image base is 0 and there is no `.reloc` section, the firmware may load us anywhere,
+//! and we relocate ourselves on entry.
+
+use core::arch::global_asm;
+
+global_asm!(
+	include_str!("efi.s"),
+	IMAGE_FILE_MACHINE_RISCV64 = const 0x5064,
+	IMAGE_SUBSYSTEM_EFI_APPLICATION = const 10,
+	R_RISCV_RELATIVE = const 3,
+);
@@ -0,0 +1,132 @@
+.equ IMAGE_FILE_EXECUTABLE_IMAGE, 0x0002
+.equ IMAGE_FILE_LINE_NUMS_STRIPPED, 0x0004
+.equ IMAGE_FILE_DEBUG_STRIPPED, 0x0200
+
+.equ IMAGE_SCN_CNT_CODE, 0x00000020
+.equ IMAGE_SCN_CNT_INITIALIZED_DATA, 0x00000040
+.equ IMAGE_SCN_MEM_EXECUTE, 0x20000000
+.equ IMAGE_SCN_MEM_READ, 0x40000000
+.equ IMAGE_SCN_MEM_WRITE, 0x80000000
+
+.section .head, "a"
+
+// The MS-DOS header
+// See https://learn.microsoft.com/en-us/windows/win32/debug/pe-format
+// The RVAs and sizes are computed by the linker script.
+dos_header:
+	.ascii	"MZ"
+	.fill	0x3a, 1, 0
+	.long	pe_header - dos_header  // e_lfanew
+
+// The PE signature and COFF file header
+pe_header:
+	.ascii	"PE\0\0"
+	.short	{IMAGE_FILE_MACHINE_RISCV64}  // Machine
+	.short	section_count  // NumberOfSections
+	.long	0  // TimeDateStamp
+	.long	0  // PointerToSymbolTable
+	.long	0  // NumberOfSymbols
+	.short	section_table - optional_header  // SizeOfOptionalHeader
+	.short	IMAGE_FILE_EXECUTABLE_IMAGE | IMAGE_FILE_LINE_NUMS_STRIPPED | IMAGE_FILE_DEBUG_STRIPPED  // Characteristics
+
+// The PE32+ optional header
+optional_header:
+	.short	0x20b  // Magic
+	.byte	0  // MajorLinkerVersion
+	.byte	0  // MinorLinkerVersion
+	.long	__pe_text_size  // SizeOfCode
+	.long	__pe_data_virtual_size  // SizeOfInitializedData
+	.long	0  // SizeOfUninitializedData
+	.long	__pe_entry  // AddressOfEntryPoint
+	.long	__pe_text_rva  // BaseOfCode
+	.quad	0  // ImageBase
+	.long	0x1000  // SectionAlignment
+	.long	0x200  // FileAlignment
+	.short	0  // MajorOperatingSystemVersion
+	.short	0  // MinorOperatingSystemVersion
+	.short	0  // MajorImageVersion
+	.short	0  // MinorImageVersion
+	.short	0  // MajorSubsystemVersion
+	.short	0  // MinorSubsystemVersion
+	.long	0  // Win32VersionValue
+	.long	__image_size  // SizeOfImage
+	.long	__pe_text_rva  // SizeOfHeaders
+	.long	0  // CheckSum
+	.short	{IMAGE_SUBSYSTEM_EFI_APPLICATION}  // Subsystem
+	.short	0  // DllCharacteristics
+	.quad	0  // SizeOfStackReserve
+	.quad	0  // SizeOfStackCommit
+	.quad	0  // SizeOfHeapReserve
+	.quad	0  // SizeOfHeapCommit
+	.long	0  // LoaderFlags
+	.long	(section_table - data_directories) / 8  // NumberOfRvaAndSizes
+
+data_directories:
+	.quad	0  // Export Table
+	.quad	0  // Import Table
+	.quad	0  // Resource Table
+	.quad	0  // Exception Table
+	.quad	0  // Certificate Table
+	.quad	0  // Base Relocation Table
+
+section_table:
+	.ascii	".text\0\0\0"  // Name
+	.long	__pe_text_size  // VirtualSize
+	.long	__pe_text_rva  // VirtualAddress
+	.long	__pe_text_size  // SizeOfRawData
+	.long	__pe_text_rva  // PointerToRawData
+	.long	0  // PointerToRelocations
+	.long	0  // PointerToLinenumbers
+	.short	0  // NumberOfRelocations
+	.short	0  // NumberOfLinenumbers
+	.long	IMAGE_SCN_CNT_CODE | IMAGE_SCN_MEM_EXECUTE | IMAGE_SCN_MEM_READ  // Characteristics
+
+	.ascii	".data\0\0\0"  // Name
+	.long	__pe_data_virtual_size  // VirtualSize
+	.long	__pe_data_rva  // VirtualAddress
+	.long	__pe_data_raw_size  // SizeOfRawData
+	.long	__pe_data_rva  // PointerToRawData
+	.long	0  // PointerToRelocations
+	.long	0  // PointerToLinenumbers
+	.short	0  // NumberOfRelocations
+	.short	0  // NumberOfLinenumbers
+	.long	IMAGE_SCN_CNT_INITIALIZED_DATA | IMAGE_SCN_MEM_READ | IMAGE_SCN_MEM_WRITE  // Characteristics
+
+.set section_count, (. - section_table) / 40
+
+.section .text.efi_entry, "ax"
+
+.globl efi_entry
+efi_entry:
+	// The firmware zeroes the BSS, since it is not part of the raw data.
+	// We may run from anywhere, so relocate ourselves by the offset from our link address.
+	// `a0` and `a1` hold the image handle and system table for `efi_main`.
+	lla	t0, __executable_start
+	lla	t1, 3f
+	ld	t1, 0(t1)
+	sub	t0, t0, t1
+	lla	t1, __rela_start
+	lla	t2, __rela_end
+	li	t6, {R_RISCV_RELATIVE}
+1:
+	bgeu	t1, t2, 2f
+	ld	t3, 0(t1)  // r_offset
+	ld	t4, 8(t1)  // r_info
+	ld	t5, 16(t1)  // r_addend
+	addi	t1, t1, 24
+	bne	t4, t6, 1b
+	add	t3, t3, t0
+	add	t5, t5, t0
+	sd	t5, 0(t3)
+	j	1b
+2:
+	// Our target uses the D extension, so make sure that the FPU is on.
+	li	t0, 1 << 13  // sstatus.FS = Initial
+	csrs	sstatus, t0
+
+	fence.i
+	j	efi_main
+
+	.balign	8
+3:
+	.dword	__link_address
@@ -1,106 +1,50 @@
 mod console;
 pub use self::console::Console;
+#[cfg(target_os = "none")]
 mod address_range;
+#[cfg(target_os = "uefi")]
+mod efi;
+#[cfg(target_os = "none")]
 mod start;
 
+cfg_if::cfg_if! {
+	if #[cfg(target_os = "none")] {
+		mod none;
+		pub use self::none::*;
+	} else if #[cfg(target_os = "uefi")] {
+		mod uefi;
+		use self::uefi::device_tree;
+	}
+}
+
 use core::arch::asm;
-use core::ptr;
 
-use address_range::AddressRange;
 use hermit_entry::Entry;
-use hermit_entry::boot_info::{
-	BootInfo, DeviceTreeAddress, HardwareInfo, PlatformInfo, RawBootInfo,
-};
-use hermit_entry::elf::LoadedKernel;
+use hermit_entry::boot_info::RawBootInfo;
 use log::info;
 
-use crate::fdt_ext::FdtExt;
-use crate::{BootInfoExt, stack};
-
-pub fn find_kernel() -> &'static [u8] {
-	let fdt = start::get_fdt();
-	fdt.find_kernel().expect("could not find kernel")
+/// Returns the current value of the `time` CSR.
+pub fn ticks() -> u64 {
+	let time: u64;
+	unsafe {
+		asm!("rdtime {time}", time = out(reg) time, options(nomem, nostack));
+	}
+	time
 }
 
-pub unsafe fn get_memory(memory_size: u64) -> u64 {
-	let memory_size = usize::try_from(memory_size).unwrap();
-
-	let initrd = AddressRange::try_from(find_kernel().as_ptr_range()).unwrap();
-	let fdt = {
-		let start = start::get_fdt_ptr();
-		let end = unsafe { start.add(start::get_fdt().total_size()) };
-		AddressRange::try_from(start..end).unwrap()
-	};
-
-	info!("initrd = {initrd}");
-	info!("fdt    = {fdt}");
-
-	const SUPERPAGE_SIZE: usize = 2 * 1024 * 1024;
-	let initrd = initrd.align_to(SUPERPAGE_SIZE);
-	let fdt = fdt.align_to(SUPERPAGE_SIZE);
-
-	let [first, second] = if initrd < fdt {
-		[initrd, fdt]
-	} else {
-		[fdt, initrd]
-	};
-
-	let start_address = if first.next(memory_size).overlaps(second) {
-		second.end()
-	} else {
-		first.end()
-	};
-
-	u64::try_from(start_address).unwrap()
+/// Returns the frequency of the `time` CSR in Hz as reported by the device tree.
+pub fn tick_frequency() -> Option<u64> {
+	let fdt = device_tree()?;
+	let cpu = fdt.cpus().next()?;
+	Some(u64::try_from(cpu.timebase_frequency()).unwrap())
 }
 
-pub unsafe fn boot_kernel(kernel_info: LoadedKernel) -> ! {
-	let LoadedKernel {
-		load_info,
-		entry_point,
-	} = kernel_info;
-
-	let fdt = start::get_fdt();
-
-	let phys_addr_range = {
-		let memory = fdt.memory();
-		let mut regions = memory.regions();
-
-		let mem_region = regions.next().unwrap();
-		assert!(
-			regions.next().is_none(),
-			"hermit-loader can only handle one memory region yet"
-		);
-
-		let mem_base = u64::try_from(mem_region.starting_address.addr()).unwrap();
-		let mem_size = u64::try_from(mem_region.size.unwrap()).unwrap();
-		mem_base..mem_base + mem_size
-	};
-
-	let device_tree = {
-		let fdt_addr = start::get_fdt_ptr().expose_provenance();
-		DeviceTreeAddress::new(fdt_addr.try_into().unwrap())
-	};
-
-	let boot_info = BootInfo {
-		hardware_info: HardwareInfo {
-			phys_addr_range,
-			serial_port_base: None,
-			device_tree,
-		},
-		load_info,
-		platform_info: PlatformInfo::LinuxBoot,
-	};
-
-	let stack = stack::get_stack_ptr();
-	let entry = ptr::with_exposed_provenance(entry_point.try_into().unwrap());
-	let hart_id = start::get_hart_id();
-	let raw_boot_info = boot_info.write();
-
-	unsafe { enter_kernel(stack, entry, hart_id, raw_boot_info) }
+/// Returns `None`, since there is no generally available entropy source in S-mode.
+pub fn random_u64() -> Option<u64> {
+	None
 }
 
-unsafe fn enter_kernel(
+pub unsafe fn enter_kernel(
 	stack: *mut u8,
 	entry: *const (),
 	hart_id: usize,
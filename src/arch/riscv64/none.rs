@@ -0,0 +1,142 @@
+use alloc::vec::Vec;
+use core::ptr;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use align_address::Align;
+use fdt::Fdt;
+use hermit_entry::boot_info::{BootInfo, DeviceTreeAddress, HardwareInfo, PlatformInfo};
+use hermit_entry::elf::LoadedKernel;
+use log::info;
+
+use super::address_range::AddressRange;
+use super::{enter_kernel, start};
+use crate::fdt_ext::{self, FdtExt};
+use crate::{BootInfoExt, stack};
+
+pub(super) fn device_tree() -> Option<Fdt<'static>> {
+	Some(start::get_fdt())
+}
+
+pub fn find_kernel() -> &'static [u8] {
+	let fdt = start::get_fdt();
+	fdt.find_kernel().expect("could not find kernel")
+}
+
+/// End of the most recent allocation
+static NEXT_FREE: AtomicUsize = AtomicUsize::new(0);
+
+const SUPERPAGE_SIZE: usize = 2 * 1024 * 1024;
+
+/// Returns the start of the next allocation of `memory_size` bytes and the end of the free memory after it.
+fn next_free(memory_size: usize) -> (usize, usize) {
+	let loader = {
+		let start = elf_symbols::executable_start().cast_const();
+		let end = elf_symbols::executable_end().cast_const();
+		AddressRange::try_from(start..end).unwrap()
+	};
+	let initrd = AddressRange::try_from(find_kernel().as_ptr_range()).unwrap();
+	let fdt = {
+		let start = start::get_fdt_ptr();
+		let end = unsafe { start.add(start::get_fdt().total_size()) };
+		AddressRange::try_from(start..end).unwrap()
+	};
+
+	info!("loader = {loader}");
+	info!("initrd = {initrd}");
+	info!("fdt    = {fdt}");
+
+	let loader = loader.align_to(SUPERPAGE_SIZE);
+	let initrd = initrd.align_to(SUPERPAGE_SIZE);
+	let fdt = fdt.align_to(SUPERPAGE_SIZE);
+
+	let first = if initrd < fdt { initrd } else { fdt };
+
+	// Memory is handed out after `first` and any previous allocation.
+	let next_free = NEXT_FREE.load(Ordering::Relaxed);
+	let start = first.end().max(next_free);
+
+	// Since we may be loaded anywhere, we also need to skip ourselves.
+	// The kernel reads the additional modules after booting.
+	let reserved = [loader, initrd, fdt]
+		.map(|range| range.start() as u64..range.end() as u64)
+		.into_iter()
+		.chain(start::get_fdt().module_regions())
+		.collect::<Vec<_>>();
+	let (start, end) = start::get_fdt()
+		.find_free_memory(
+			start as u64,
+			memory_size as u64,
+			SUPERPAGE_SIZE as u64,
+			&reserved,
+		)
+		.expect("Not enough free memory");
+
+	(start.try_into().unwrap(), end.try_into().unwrap())
+}
+
+pub unsafe fn get_memory(memory_size: u64) -> u64 {
+	let memory_size = usize::try_from(memory_size).unwrap();
+	let (start_address, _end) = next_free(memory_size);
+	NEXT_FREE.store(
+		(start_address + memory_size).align_up(SUPERPAGE_SIZE),
+		Ordering::Relaxed,
+	);
+
+	u64::try_from(start_address).unwrap()
+}
+
+/// Returns the number of bytes of free memory after the next allocation of `memory_size` bytes.
+pub fn free_memory_after(memory_size: u64) -> u64 {
+	let memory_size = usize::try_from(memory_size).unwrap();
+	let (start, end) = next_free(memory_size);
+	u64::try_from(end.saturating_sub(start + memory_size)).unwrap()
+}
+
+pub fn command_line() -> Option<&'static str> {
+	let fdt = start::get_fdt();
+	fdt.find_node("/chosen")?.property("bootargs")?.as_str()
+}
+
+pub unsafe fn boot_kernel(
+	kernel_info: LoadedKernel,
+	bootargs: Option<&'static str>,
+	app_sha256: [u8; 32],
+) -> ! {
+	let LoadedKernel {
+		load_info,
+		entry_point,
+	} = kernel_info;
+
+	let fdt_ptr =
+		unsafe { fdt_ext::prepare(start::get_fdt_ptr().cast_mut(), bootargs, app_sha256) };
+
+	let fdt = unsafe { fdt::Fdt::from_ptr(fdt_ptr).unwrap() };
+	fdt.log_additional_modules();
+
+	for region in fdt.memory_regions() {
+		info!("Memory region: {region:#x?}");
+	}
+	let phys_addr_range = fdt.phys_addr_range();
+
+	let device_tree = {
+		let fdt_addr = fdt_ptr.expose_provenance();
+		DeviceTreeAddress::new(fdt_addr.try_into().unwrap())
+	};
+
+	let boot_info = BootInfo {
+		hardware_info: HardwareInfo {
+			phys_addr_range,
+			serial_port_base: None,
+			device_tree,
+		},
+		load_info,
+		platform_info: PlatformInfo::LinuxBoot,
+	};
+
+	let stack = stack::get_stack_ptr();
+	let entry = ptr::with_exposed_provenance(entry_point.try_into().unwrap());
+	let hart_id = start::get_hart_id();
+	let raw_boot_info = boot_info.write();
+
+	unsafe { enter_kernel(stack, entry, hart_id, raw_boot_info) }
+}
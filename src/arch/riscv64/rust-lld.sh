@@ -0,0 +1,12 @@
+#!/bin/sh
+# Runs rust-lld without `--nxcompat`.
+#
+# rustc treats all UEFI targets like Windows and passes `--nxcompat`, which ELF lld does not know.
+# We link an ELF file, since LLVM cannot emit RISC-V COFF objects.
+
+for arg; do
+	shift
+	[ "$arg" = "--nxcompat" ] || set -- "$@" "$arg"
+done
+
+exec rust-lld "$@"
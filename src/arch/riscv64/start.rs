@@ -30,13 +30,69 @@ pub fn get_fdt() -> Fdt<'static> {
 #[link_section = ".init"]
 pub unsafe extern "C" fn _start(hart_id: usize, fdt: *const u8) -> ! {
 	asm!(
+		// The RISC-V Linux Image header
+		// See https://docs.kernel.org/arch/riscv/boot-image-header.html
+		".option push",
+		".option norvc",
+		"j       2f",             // code0
+		".option pop",
+		".word   0",              // code1
+		".dword  {TEXT_OFFSET}",  // text_offset
+		".dword  __image_size",   // image_size
+		".dword  0",              // flags
+		".word   {VERSION}",      // version
+		".word   0",              // res1
+		".dword  0",              // res2
+		".ascii  \"RISCV\\0\\0\\0\"", // magic
+		".ascii  \"RSC\\x05\"", // magic2
+		".word   0",              // res3
+
+		// Bootloaders do not clear the BSS when loading an Image.
+		"2:",
+		"lla     t0, __bss_start",
+		"lla     t1, _end",
+		"3:",
+		"bgeu    t0, t1, 4f",
+		"sd      zero, 0(t0)",
+		"addi    t0, t0, 8",
+		"j       3b",
+		"4:",
+
+		// We may run from anywhere, so relocate ourselves by the offset from our link address.
+		"lla     t0, _start",
+		"lla     t1, 7f",
+		"ld      t1, 0(t1)",
+		"sub     t0, t0, t1",
+		"lla     t1, __rela_start",
+		"lla     t2, __rela_end",
+		"li      t6, {R_RISCV_RELATIVE}",
+		"5:",
+		"bgeu    t1, t2, 6f",
+		"ld      t3, 0(t1)",  // r_offset
+		"ld      t4, 8(t1)",  // r_info
+		"ld      t5, 16(t1)", // r_addend
+		"addi    t1, t1, 24",
+		"bne     t4, t6, 5b",
+		"add     t3, t3, t0",
+		"add     t5, t5, t0",
+		"sd      t5, 0(t3)",
+		"j       5b",
+		"6:",
+
 		// Initialize stack
-		"la      sp, {BOOT_STACK}",
+		"lla     sp, {BOOT_STACK}",
 		"li      t0, {STACK_SIZE}",
 		"add     sp, sp, t0",
 
 		"j       {start}",
 
+		".balign 8",
+		"7:",
+		".dword  __link_address",
+
+		TEXT_OFFSET = const 0x200000,
+		VERSION = const 0x2,
+		R_RISCV_RELATIVE = const 3,
 		BOOT_STACK = sym STACK,
 		STACK_SIZE = const Stack::SIZE,
 		start = sym start,
@@ -0,0 +1,6 @@
+use fdt::Fdt;
+
+/// Returns the firmware's device tree.
+pub(super) fn device_tree() -> Option<Fdt<'static>> {
+	crate::os::device_tree().map(|dtb| Fdt::new(dtb).unwrap())
+}
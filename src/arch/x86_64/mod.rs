@@ -12,7 +12,7 @@ mod stack;
 pub use console::Console;
 
 #[cfg(target_os = "none")]
-pub use self::platform::{boot_kernel, find_kernel};
+pub use self::platform::{boot_kernel, command_line, find_kernel};
 
 #[cfg(target_os = "none")]
 const KERNEL_STACK_SIZE: u64 = 32_768;
@@ -29,6 +29,93 @@ pub unsafe fn get_memory(memory_size: u64) -> u64 {
 	address as u64
 }
 
+/// Mirrors the console to `framebuffer` if the framebuffer console is enabled.
+#[cfg(target_os = "none")]
+#[cfg_attr(feature = "pvh", expect(dead_code))]
+fn init_framebuffer_console(framebuffer: crate::framebuffer::Framebuffer) {
+	use crate::framebuffer::console::{self, FramebufferConsole};
+
+	if !console::enabled() {
+		return;
+	}
+
+	let start = usize::try_from(framebuffer.address).unwrap();
+	let end = start + usize::try_from(framebuffer.size()).unwrap();
+	page_tables::identity_map_mmio(start..end);
+
+	let console = unsafe { FramebufferConsole::new(framebuffer) };
+	crate::os::CONSOLE.lock().set_framebuffer(console);
+}
+
+/// Returns the current value of the time-stamp counter.
+pub fn ticks() -> u64 {
+	unsafe { core::arch::x86_64::_rdtsc() }
+}
+
+/// Returns the frequency of the time-stamp counter in Hz, if the CPU reports it.
+pub fn tick_frequency() -> Option<u64> {
+	use core::arch::x86_64::__cpuid;
+
+	let max_leaf = __cpuid(0).eax;
+
+	if max_leaf >= 0x15 {
+		let tsc = __cpuid(0x15);
+		if tsc.eax != 0 && tsc.ebx != 0 && tsc.ecx != 0 {
+			return Some(u64::from(tsc.ecx) * u64::from(tsc.ebx) / u64::from(tsc.eax));
+		}
+	}
+
+	if max_leaf >= 0x16 {
+		let base_mhz = __cpuid(0x16).eax & 0xffff;
+		if base_mhz != 0 {
+			return Some(u64::from(base_mhz) * 1_000_000);
+		}
+	}
+
+	None
+}
+
+/// Returns a random number from `RDSEED` or `RDRAND`, if the CPU supports either.
+pub fn random_u64() -> Option<u64> {
+	use core::arch::x86_64::{__cpuid, __cpuid_count, _rdrand64_step, _rdseed64_step};
+
+	// Both instructions may fail transiently, so we retry as recommended by Intel.
+	const RETRIES: usize = 10;
+
+	let has_rdrand = __cpuid(1).ecx & (1 << 30) != 0;
+	let has_rdseed = __cpuid(0).eax >= 7 && __cpuid_count(7, 0).ebx & (1 << 18) != 0;
+
+	let mut value = 0;
+	if has_rdseed {
+		for _ in 0..RETRIES {
+			if unsafe { _rdseed64_step(&mut value) } == 1 {
+				return Some(value);
+			}
+		}
+	}
+	if has_rdrand {
+		for _ in 0..RETRIES {
+			if unsafe { _rdrand64_step(&mut value) } == 1 {
+				return Some(value);
+			}
+		}
+	}
+
+	None
+}
+
+/// Returns the number of bytes of free memory after the next allocation of `memory_size` bytes.
+#[cfg(target_os = "none")]
+pub fn free_memory_after(memory_size: u64) -> u64 {
+	use align_address::Align;
+	use x86_64::structures::paging::{PageSize, Size2MiB};
+
+	use self::physicalmem::PhysAlloc;
+
+	let memory_size = (memory_size as usize).align_up(Size2MiB::SIZE as usize);
+	PhysAlloc::free().saturating_sub(memory_size) as u64
+}
+
 pub unsafe fn enter_kernel(
 	stack: *mut u8,
 	entry: *const (),
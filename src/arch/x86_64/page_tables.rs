@@ -27,6 +27,7 @@ use core::ops::Range;
 use core::{fmt, ptr};
 
 use log::{debug, info, warn};
+use x86_64::structures::paging::mapper::MapToError;
 use x86_64::structures::paging::{
 	Mapper, OffsetPageTable, PageSize, PageTableFlags, PhysFrame, Size1GiB, Size2MiB,
 };
@@ -115,6 +116,34 @@ pub unsafe fn init(max_phys_addr: usize) {
 	}
 }
 
+/// Identity-maps the memory-mapped I/O region `phys_addrs`.
+///
+/// Parts of `phys_addrs` that are already mapped are left unchanged.
+pub fn identity_map_mmio(phys_addrs: Range<usize>) {
+	let start_addr = PhysAddr::new(phys_addrs.start as u64);
+	let last_addr = PhysAddr::new((phys_addrs.end - 1) as u64);
+
+	let start = PhysFrame::<Size2MiB>::containing_address(start_addr);
+	let last = PhysFrame::<Size2MiB>::containing_address(last_addr);
+
+	info!("Identity-mapping MMIO {start:?}..={last:?}");
+
+	let level_4_table = unsafe { &mut *(&raw mut LEVEL_4_TABLE).cast() };
+	let phys_offset = VirtAddr::new(0);
+	let mut page_table = unsafe { OffsetPageTable::new(level_4_table, phys_offset) };
+
+	let flags = PageTableFlags::PRESENT | PageTableFlags::WRITABLE;
+
+	for frame in PhysFrame::range_inclusive(start, last) {
+		// SAFETY: We are mapping memory-mapped I/O, which is not used as RAM.
+		match unsafe { page_table.identity_map(frame, flags, &mut PhysAlloc) } {
+			Ok(flush) => flush.flush(),
+			Err(MapToError::PageAlreadyMapped(_) | MapToError::ParentEntryHugePage) => {}
+			Err(err) => panic!("Could not map {frame:?}: {err:?}"),
+		}
+	}
+}
+
 fn identity_map<S: PageSize + fmt::Debug>(phys_addrs: Range<usize>)
 where
 	for<'a> OffsetPageTable<'a>: Mapper<S>,
@@ -8,11 +8,13 @@ static PHYS_ALLOC: OneShotMutex<Option<PhysAllocInner>> = OneShotMutex::new(None
 
 struct PhysAllocInner {
 	next: NonZeroUsize,
+	/// End of the free memory region that `next` starts in.
+	end: usize,
 }
 
 impl PhysAllocInner {
-	pub fn new(addr: NonZeroUsize) -> Self {
-		Self { next: addr }
+	pub fn new(addr: NonZeroUsize, end: usize) -> Self {
+		Self { next: addr, end }
 	}
 
 	pub fn allocate(&mut self, size: usize) -> usize {
@@ -27,15 +29,23 @@ impl PhysAllocInner {
 pub struct PhysAlloc;
 
 impl PhysAlloc {
-	pub fn init(addr: usize) {
+	/// Initializes the allocator with the free memory in `addr..end`.
+	pub fn init(addr: usize, end: usize) {
 		let mut phys_alloc = PHYS_ALLOC.lock();
 		assert!(phys_alloc.is_none());
-		phys_alloc.replace(PhysAllocInner::new(addr.try_into().unwrap()));
+		phys_alloc.replace(PhysAllocInner::new(addr.try_into().unwrap(), end));
 	}
 
 	pub fn allocate(size: usize) -> usize {
 		PHYS_ALLOC.lock().as_mut().unwrap().allocate(size)
 	}
+
+	/// Returns the number of bytes of free memory.
+	pub fn free() -> usize {
+		let phys_alloc = PHYS_ALLOC.lock();
+		let phys_alloc = phys_alloc.as_ref().unwrap();
+		phys_alloc.end.saturating_sub(phys_alloc.next.get())
+	}
 }
 
 unsafe impl<S: PageSize> FrameAllocator<S> for PhysAlloc {
@@ -2,21 +2,23 @@ use alloc::borrow::ToOwned;
 use core::ffi::CStr;
 use core::ptr::write_bytes;
 use core::sync::atomic::{AtomicPtr, Ordering};
-use core::{ptr, slice};
+use core::{iter, mem, ptr, slice};
 
 use align_address::Align;
 use hermit_entry::boot_info::{
 	BootInfo, DeviceTreeAddress, HardwareInfo, PlatformInfo, SerialPortBase,
 };
 use hermit_entry::elf::LoadedKernel;
-use linux_boot_params::{BootE820Entry, BootParams};
-use log::{error, info};
+use linux_boot_params::{BootE820Entry, BootParams, E820Type};
+use log::{error, info, warn};
 use x86_64::structures::paging::{PageSize, Size2MiB, Size4KiB};
 
-use crate::BootInfoExt;
 use crate::arch::x86_64::physicalmem::PhysAlloc;
 use crate::arch::x86_64::{KERNEL_STACK_SIZE, SERIAL_IO_PORT, page_tables};
 use crate::fdt::Fdt;
+use crate::framebuffer::{Framebuffer, PixelFormat};
+use crate::reserved_memory::{Reservation, ReservedMemory};
+use crate::{BootInfoExt, random};
 
 mod entry {
 	core::arch::global_asm!(
@@ -37,17 +39,35 @@ unsafe extern "C" fn rust_start(boot_params: *mut BootParams) -> ! {
 	crate::log::init();
 	BOOT_PARAMS.store(boot_params, Ordering::Relaxed);
 
+	let boot_params_ref = unsafe { BootParams::get() };
+	let e820_entries = boot_params_ref.e820_entries();
+
 	let loader_end = elf_symbols::executable_end();
 	let free_addr = loader_end.addr().align_up(Size2MiB::SIZE as usize);
+	// The ramdisk and the setup_data list are read after PhysAlloc has been used.
+	let ramdisk_image = boot_params_ref.hdr.ramdisk_image as usize;
+	let setup_data = boot_params_ref
+		.setup_data()
+		.map(|setup_data| setup_data.header_addr());
+	let free_end = e820_entries
+		.clone()
+		.filter(|entry| {
+			let typ = entry.typ;
+			typ == E820Type::Ram
+		})
+		.map(|entry| entry.addr as usize..(entry.addr + entry.size) as usize)
+		.find(|range| range.contains(&free_addr))
+		.map_or(free_addr, |range| {
+			iter::once(ramdisk_image)
+				.chain(setup_data)
+				.filter(|addr| (free_addr..range.end).contains(addr))
+				.fold(range.end, usize::min)
+		});
 	// Memory after the highest end address is unused and available for the physical memory manager.
-	info!("Intializing PhysAlloc with {free_addr:#x}");
-	PhysAlloc::init(free_addr);
+	info!("Intializing PhysAlloc with {free_addr:#x}..{free_end:#x}");
+	PhysAlloc::init(free_addr, free_end);
 
-	let boot_params_ref = unsafe { BootParams::get() };
-	let e820_entries = boot_params_ref.e820_entries();
 	let max_phys_addr = e820_entries
-		.iter()
-		.copied()
 		.map(|entry| entry.addr + entry.size)
 		.max()
 		.unwrap();
@@ -55,6 +75,10 @@ unsafe extern "C" fn rust_start(boot_params: *mut BootParams) -> ! {
 		page_tables::init(max_phys_addr.try_into().unwrap());
 	}
 
+	if let Some(framebuffer) = boot_params_ref.framebuffer() {
+		crate::arch::x86_64::init_framebuffer_console(framebuffer);
+	}
+
 	unsafe {
 		crate::os::loader_main();
 	}
@@ -71,7 +95,17 @@ pub fn find_kernel() -> &'static [u8] {
 	boot_params_ref.map_ramdisk().unwrap()
 }
 
-pub unsafe fn boot_kernel(kernel_info: LoadedKernel) -> ! {
+pub fn command_line() -> Option<&'static str> {
+	let boot_params_ref = unsafe { BootParams::get() };
+	let command_line = boot_params_ref.map_cmdline().to_str().unwrap();
+	Some(command_line)
+}
+
+pub unsafe fn boot_kernel(
+	kernel_info: LoadedKernel,
+	bootargs: Option<&'static str>,
+	app_sha256: [u8; 32],
+) -> ! {
 	let LoadedKernel {
 		load_info,
 		entry_point,
@@ -90,34 +124,134 @@ pub unsafe fn boot_kernel(kernel_info: LoadedKernel) -> ! {
 
 	let mut fdt = Fdt::new("linux").unwrap();
 
+	// Device trees have to be merged before adding any nodes.
+	for setup_data in boot_params_ref.setup_data() {
+		if setup_data.typ == SETUP_DTB {
+			let data = unsafe { &*setup_data.data };
+			info!("setup_data: {:#x} bytes of device tree", data.len());
+			fdt = fdt.merge(data).unwrap();
+		}
+	}
+
+	let boot_params_addr = BOOT_PARAMS.load(Ordering::Relaxed).expose_provenance() as u64;
+	let boot_params_end = boot_params_addr + mem::size_of::<BootParams>() as u64;
+	fdt = fdt
+		.reserved_memory(ReservedMemory::loader())
+		.unwrap()
+		.reserved_memory(ReservedMemory::new(
+			boot_params_addr..boot_params_end,
+			"boot-params",
+			Reservation::Reclaimable,
+		))
+		.unwrap()
+		.reserved_memory(ReservedMemory::reclaimable(
+			boot_params_ref.map_cmdline().to_bytes_with_nul(),
+			"cmdline",
+		))
+		.unwrap()
+		.reserved_memory(ReservedMemory::reclaimable(
+			boot_params_ref.map_ramdisk().unwrap(),
+			"initrd",
+		))
+		.unwrap();
+
 	let e820_entries = boot_params_ref.e820_entries();
-	assert!(!e820_entries.is_empty());
+	assert!(e820_entries.clone().next().is_some());
 
-	for entry in e820_entries.iter().copied() {
+	for entry in e820_entries.clone() {
 		let BootE820Entry { addr, size, typ } = entry;
 		info!("E820 memory region: addr = {addr:>#11x}, size = {size:>#11x}, type = {typ:?}");
 		let memory = addr..addr + size;
-		fdt = fdt.memory(memory).unwrap();
+		fdt = match typ {
+			E820Type::Ram => fdt.memory(memory).unwrap(),
+			E820Type::Acpi => fdt
+				.reserved_memory(ReservedMemory::new(
+					memory,
+					"acpi-reclaimable",
+					Reservation::Reclaimable,
+				))
+				.unwrap(),
+			E820Type::Nvs => fdt
+				.reserved_memory(ReservedMemory::new(
+					memory,
+					"acpi-nvs",
+					Reservation::Permanent,
+				))
+				.unwrap(),
+			_ => fdt,
+		};
 	}
 
-	let start = e820_entries
-		.iter()
-		.copied()
+	let ram = e820_entries.filter(|entry| {
+		let typ = entry.typ;
+		typ == E820Type::Ram
+	});
+
+	let start = ram
+		.clone()
 		.map(|entry| entry.addr)
 		.find(|addr| *addr != 0)
 		.unwrap();
 
-	let end = e820_entries
-		.iter()
-		.copied()
-		.map(|entry| entry.addr + entry.size)
-		.max()
-		.unwrap();
+	let end = ram.map(|entry| entry.addr + entry.size).max().unwrap();
 
 	let phys_addr_range = start..end;
 
-	let command_line = boot_params_ref.map_cmdline().to_str().unwrap();
-	fdt = fdt.bootargs(command_line.to_owned()).unwrap();
+	if let Some(bootargs) = bootargs {
+		fdt = fdt.bootargs(bootargs.to_owned()).unwrap();
+	}
+
+	if let Some(framebuffer) = boot_params_ref.framebuffer() {
+		fdt = fdt.framebuffer(framebuffer).unwrap();
+	}
+
+	let mut setup_rng_seed = None;
+	for setup_data in boot_params_ref.setup_data() {
+		let data = unsafe { &mut *setup_data.data };
+
+		let start = (data.as_ptr().addr() - mem::size_of::<SetupDataHeader>()) as u64;
+		let end = data.as_ptr_range().end.addr() as u64;
+		fdt = fdt
+			.reserved_memory(ReservedMemory::new(
+				start..end,
+				"setup-data",
+				Reservation::Reclaimable,
+			))
+			.unwrap();
+
+		match setup_data.typ {
+			SETUP_E820_EXT => {
+				info!(
+					"setup_data: {:#x} bytes of extended E820 entries",
+					data.len()
+				);
+			}
+			SETUP_DTB => {}
+			SETUP_RNG_SEED => {
+				info!("setup_data: {:#x} bytes of rng-seed", data.len());
+				setup_rng_seed = Some(data.to_vec());
+				// Like Linux, we make sure that the seed is not reused.
+				data.fill(0);
+			}
+			typ => warn!("setup_data: Ignoring unknown entry of type {typ}"),
+		}
+	}
+
+	let rng_seed = match (random::seed(), setup_rng_seed) {
+		(Some(mut rng_seed), Some(setup_rng_seed)) => {
+			for (byte, setup_byte) in rng_seed.iter_mut().zip(setup_rng_seed.iter().cycle()) {
+				*byte ^= setup_byte;
+			}
+			Some(rng_seed.to_vec())
+		}
+		(Some(rng_seed), None) => Some(rng_seed.to_vec()),
+		(None, setup_rng_seed) => setup_rng_seed,
+	};
+	if let Some(rng_seed) = rng_seed {
+		fdt = fdt.rng_seed(&rng_seed).unwrap();
+	}
+
+	fdt = fdt.app_sha256(app_sha256).unwrap();
 
 	let fdt = fdt.finish().unwrap();
 
@@ -132,7 +266,7 @@ pub unsafe fn boot_kernel(kernel_info: LoadedKernel) -> ! {
 		},
 		load_info,
 		platform_info: PlatformInfo::LinuxBootParams {
-			command_line: Some(command_line),
+			command_line: bootargs,
 			boot_params_addr: u64::try_from(
 				BOOT_PARAMS.load(Ordering::Relaxed).expose_provenance(),
 			)
@@ -148,13 +282,46 @@ pub unsafe fn boot_kernel(kernel_info: LoadedKernel) -> ! {
 	unsafe { crate::arch::x86_64::enter_kernel(stack, entry, raw_boot_info) }
 }
 
+/// `setup_data` types from Linux' `arch/x86/include/uapi/asm/setup_data.h`.
+const SETUP_E820_EXT: u32 = 1;
+const SETUP_DTB: u32 = 2;
+const SETUP_RNG_SEED: u32 = 9;
+
+/// Video types and capabilities from Linux' `include/uapi/linux/screen_info.h`.
+const VIDEO_TYPE_VLFB: u8 = 0x23;
+const VIDEO_TYPE_EFI: u8 = 0x70;
+const VIDEO_CAPABILITY_64BIT_BASE: u32 = 1 << 1;
+
+/// The header of an entry of the `setup_data` linked list.
+#[repr(C)]
+struct SetupDataHeader {
+	next: u64,
+	typ: u32,
+	len: u32,
+}
+
+/// An entry of the `setup_data` linked list.
+struct SetupData {
+	typ: u32,
+	data: *mut [u8],
+}
+
+impl SetupData {
+	/// The address of the `setup_data` header in front of the payload.
+	fn header_addr(&self) -> usize {
+		self.data.addr() - mem::size_of::<SetupDataHeader>()
+	}
+}
+
 trait BootParamsExt {
 	unsafe fn map();
 	unsafe fn get() -> &'static Self;
 	fn supported(&self) -> bool;
 	fn map_ramdisk(&self) -> Option<&[u8]>;
 	fn map_cmdline(&self) -> &CStr;
-	fn e820_entries(&self) -> &[BootE820Entry];
+	fn e820_entries(&self) -> impl Iterator<Item = BootE820Entry> + Clone;
+	fn setup_data(&self) -> impl Iterator<Item = SetupData> + Clone;
+	fn framebuffer(&self) -> Option<Framebuffer>;
 }
 
 impl BootParamsExt for BootParams {
@@ -222,8 +389,74 @@ impl BootParamsExt for BootParams {
 		CStr::from_bytes_until_nul(bytes).unwrap()
 	}
 
-	fn e820_entries(&self) -> &[BootE820Entry] {
+	fn e820_entries(&self) -> impl Iterator<Item = BootE820Entry> + Clone {
 		let e820_entries = self.e820_entries as usize;
-		&self.e820_table[..e820_entries]
+
+		// Memory maps with more than 128 entries continue in `setup_data`.
+		let e820_ext = self
+			.setup_data()
+			.filter(|setup_data| setup_data.typ == SETUP_E820_EXT)
+			.flat_map(|setup_data| {
+				let data = unsafe { &*setup_data.data };
+				data.chunks_exact(mem::size_of::<BootE820Entry>())
+					.map(|entry| unsafe { entry.as_ptr().cast::<BootE820Entry>().read_unaligned() })
+			});
+
+		self.e820_table[..e820_entries]
+			.iter()
+			.copied()
+			.chain(e820_ext)
+	}
+
+	fn setup_data(&self) -> impl Iterator<Item = SetupData> + Clone {
+		let mut next = self.hdr.setup_data;
+		iter::from_fn(move || {
+			if next == 0 {
+				return None;
+			}
+
+			let ptr = ptr::with_exposed_provenance_mut::<SetupDataHeader>(next.try_into().unwrap());
+			let header = unsafe { ptr.read_unaligned() };
+			next = header.next;
+
+			let data = unsafe { ptr.add(1).cast::<u8>() };
+			let data = ptr::slice_from_raw_parts_mut(data, header.len.try_into().unwrap());
+			Some(SetupData {
+				typ: header.typ,
+				data,
+			})
+		})
+	}
+
+	fn framebuffer(&self) -> Option<Framebuffer> {
+		let screen_info = self.screen_info;
+		let video_type = screen_info.orig_video_is_vga;
+		if video_type != VIDEO_TYPE_VLFB && video_type != VIDEO_TYPE_EFI {
+			return None;
+		}
+
+		let mut address = u64::from(screen_info.lfb_base);
+		if screen_info.capabilities & VIDEO_CAPABILITY_64BIT_BASE != 0 {
+			address |= u64::from(screen_info.ext_lfb_base) << 32;
+		}
+
+		let bpp = u8::try_from(screen_info.lfb_depth).ok()?;
+		let rgb = [
+			(screen_info.red_pos, screen_info.red_size),
+			(screen_info.green_pos, screen_info.green_size),
+			(screen_info.blue_pos, screen_info.blue_size),
+		];
+		let Some(format) = PixelFormat::from_rgb(bpp, rgb) else {
+			warn!("Unsupported framebuffer format: {bpp} bpp, rgb = {rgb:?}");
+			return None;
+		};
+
+		Some(Framebuffer {
+			address,
+			width: screen_info.lfb_width.into(),
+			height: screen_info.lfb_height.into(),
+			stride: screen_info.lfb_linelength.into(),
+			format,
+		})
 	}
 }
@@ -5,5 +5,11 @@ cfg_if::cfg_if! {
 	} else if #[cfg(feature = "multiboot")] {
 		mod multiboot;
 		pub use self::multiboot::*;
+	} else if #[cfg(feature = "multiboot2")] {
+		mod multiboot2;
+		pub use self::multiboot2::*;
+	} else if #[cfg(feature = "pvh")] {
+		mod pvh;
+		pub use self::pvh::*;
 	}
 }
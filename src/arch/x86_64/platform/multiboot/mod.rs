@@ -8,20 +8,25 @@ use hermit_entry::boot_info::{
 	BootInfo, DeviceTreeAddress, HardwareInfo, PlatformInfo, SerialPortBase,
 };
 use hermit_entry::elf::LoadedKernel;
-use log::info;
-use multiboot::information::{MemoryManagement, MemoryType, Multiboot, MultibootInfo, PAddr};
+use log::{info, warn};
+use multiboot::information::{
+	ColorInfoType, MemoryManagement, MemoryType, Multiboot, MultibootInfo, PAddr,
+};
 use vm_fdt::FdtWriterResult;
 use x86_64::structures::paging::{PageSize, Size2MiB, Size4KiB};
 
-use crate::BootInfoExt;
 use crate::arch::x86_64::physicalmem::PhysAlloc;
 use crate::arch::x86_64::{KERNEL_STACK_SIZE, SERIAL_IO_PORT, page_tables};
 use crate::fdt::Fdt;
+use crate::framebuffer::{Framebuffer, PixelFormat};
+use crate::reserved_memory::{Reservation, ReservedMemory};
+use crate::{BootInfoExt, random};
 
 #[allow(bad_asm_style)]
 mod entry {
 	core::arch::global_asm!(
 		include_str!("entry.s"),
+		include_str!("../start32.s"),
 		rust_start = sym super::rust_start,
 		stack = sym crate::arch::x86_64::stack::STACK,
 		stack_top_offset = const crate::arch::x86_64::stack::Stack::top_offset(),
@@ -41,8 +46,17 @@ unsafe extern "C" fn rust_start(mb_info: *mut MultibootInfo) -> ! {
 	let mut mem = Mem;
 	let multiboot = unsafe { Multiboot::from_ref(&mut *mb_info, &mut mem) };
 	let highest_address = multiboot.find_highest_address().align_up(Size2MiB::SIZE) as usize;
+	let free_end = multiboot
+		.memory_regions()
+		.unwrap()
+		.filter(|memory_region| memory_region.memory_type() == MemoryType::Available)
+		.map(|memory_region| {
+			memory_region.base_address()..memory_region.base_address() + memory_region.length()
+		})
+		.find(|memory_region| memory_region.contains(&(highest_address as u64)))
+		.map_or(highest_address, |memory_region| memory_region.end as usize);
 	// Memory after the highest end address is unused and available for the physical memory manager.
-	PhysAlloc::init(highest_address);
+	PhysAlloc::init(highest_address, free_end);
 
 	let max_phys_addr = multiboot
 		.memory_regions()
@@ -56,11 +70,45 @@ unsafe extern "C" fn rust_start(mb_info: *mut MultibootInfo) -> ! {
 		page_tables::init(max_phys_addr.try_into().unwrap());
 	}
 
+	if let Some(framebuffer) = framebuffer(&multiboot) {
+		crate::arch::x86_64::init_framebuffer_console(framebuffer);
+	}
+
 	unsafe {
 		crate::os::loader_main();
 	}
 }
 
+/// Returns the framebuffer from the Multiboot information, if its format is supported.
+fn framebuffer(multiboot: &Multiboot<'_, '_>) -> Option<Framebuffer> {
+	let framebuffer_table = multiboot.framebuffer_table()?;
+	info!("Found framebuffer: {framebuffer_table:?}");
+
+	let format = match framebuffer_table.color_info() {
+		Some(ColorInfoType::Rgb(rgb)) => PixelFormat::from_rgb(
+			framebuffer_table.bpp,
+			[
+				(rgb.red_field_position, rgb.red_mask_size),
+				(rgb.green_field_position, rgb.green_mask_size),
+				(rgb.blue_field_position, rgb.blue_mask_size),
+			],
+		),
+		_ => None,
+	};
+	let Some(format) = format else {
+		warn!("Unsupported framebuffer format");
+		return None;
+	};
+
+	Some(Framebuffer {
+		address: framebuffer_table.addr,
+		width: framebuffer_table.width,
+		height: framebuffer_table.height,
+		stride: framebuffer_table.pitch,
+		format,
+	})
+}
+
 struct Mem;
 
 impl MemoryManagement for Mem {
@@ -84,7 +132,7 @@ impl MemoryManagement for Mem {
 pub struct DeviceTree;
 
 impl DeviceTree {
-	pub fn create() -> FdtWriterResult<&'static [u8]> {
+	pub fn create(bootargs: Option<&str>, app_sha256: [u8; 32]) -> FdtWriterResult<&'static [u8]> {
 		let mb_info = MB_INFO.load(Ordering::Relaxed);
 		let mut mem = Mem;
 		let multiboot = unsafe { Multiboot::from_ptr(mb_info as u64, &mut mem).unwrap() };
@@ -95,10 +143,46 @@ impl DeviceTree {
 
 		let mut fdt = Fdt::new("multiboot")?.memory_regions(memory_regions)?;
 
-		if let Some(cmdline) = multiboot.command_line() {
-			fdt = fdt.bootargs(cmdline.to_owned())?;
+		let mb_info_addr = mb_info.expose_provenance() as u64;
+		let mb_info_end = mb_info_addr + mem::size_of::<MultibootInfo>() as u64;
+		fdt = fdt
+			.reserved_memory(ReservedMemory::loader())?
+			.reserved_memory(ReservedMemory::new(
+				mb_info_addr..mb_info_end,
+				"multiboot-info",
+				Reservation::Reclaimable,
+			))?
+			.reserved_memory(ReservedMemory::reclaimable(find_kernel(), "initrd"))?;
+		if let Some(command_line) = multiboot.command_line() {
+			let command_line =
+				unsafe { slice::from_raw_parts(command_line.as_ptr(), command_line.len() + 1) };
+			fdt = fdt.reserved_memory(ReservedMemory::reclaimable(command_line, "cmdline"))?;
+		}
+
+		// The first module is the kernel. All others are forwarded.
+		for module in multiboot.modules().into_iter().flatten().skip(1) {
+			let string = module.string.unwrap_or_default();
+			info!(
+				"Forwarding module {string:?} at [{:#x} - {:#x}]",
+				module.start, module.end
+			);
+			fdt = fdt.module(module.start..module.end, string)?;
 		}
 
+		if let Some(framebuffer) = framebuffer(&multiboot) {
+			fdt = fdt.framebuffer(framebuffer)?;
+		}
+
+		if let Some(bootargs) = bootargs {
+			fdt = fdt.bootargs(bootargs.to_owned())?;
+		}
+
+		if let Some(rng_seed) = random::seed() {
+			fdt = fdt.rng_seed(&rng_seed)?;
+		}
+
+		fdt = fdt.app_sha256(app_sha256)?;
+
 		let fdt = fdt.finish()?;
 
 		Ok(fdt.leak())
@@ -133,7 +217,18 @@ pub fn find_kernel() -> &'static [u8] {
 	unsafe { slice::from_raw_parts(ptr::with_exposed_provenance(elf_start), elf_len) }
 }
 
-pub unsafe fn boot_kernel(kernel_info: LoadedKernel) -> ! {
+pub fn command_line() -> Option<&'static str> {
+	let mut mem = Mem;
+	let mb_info = MB_INFO.load(Ordering::Relaxed);
+	let multiboot = unsafe { Multiboot::from_ptr(mb_info as u64, &mut mem).unwrap() };
+	multiboot.command_line()
+}
+
+pub unsafe fn boot_kernel(
+	kernel_info: LoadedKernel,
+	bootargs: Option<&'static str>,
+	app_sha256: [u8; 32],
+) -> ! {
 	let LoadedKernel {
 		load_info,
 		entry_point,
@@ -168,7 +263,8 @@ pub unsafe fn boot_kernel(kernel_info: LoadedKernel) -> ! {
 		write_bytes(stack, 0, KERNEL_STACK_SIZE.try_into().unwrap());
 	}
 
-	let device_tree = DeviceTree::create().expect("Unable to create devicetree!");
+	let device_tree =
+		DeviceTree::create(bootargs, app_sha256).expect("Unable to create devicetree!");
 	let device_tree =
 		DeviceTreeAddress::new(u64::try_from(device_tree.as_ptr().expose_provenance()).unwrap());
 
@@ -180,7 +276,7 @@ pub unsafe fn boot_kernel(kernel_info: LoadedKernel) -> ! {
 		},
 		load_info,
 		platform_info: PlatformInfo::Multiboot {
-			command_line,
+			command_line: bootargs,
 			multiboot_info_addr: (mb_info as u64).try_into().unwrap(),
 		},
 	};
@@ -0,0 +1,34 @@
+.code32
+
+# Multiboot2 expects its header within the first 32 KiB of the kernel.
+.section .mboot, "a"
+
+# The header and all of its tags MUST be 8 byte aligned.
+.align 8
+mboot2:
+    .set MULTIBOOT2_HEADER_MAGIC,  0xE85250D6
+    .set MULTIBOOT2_ARCH_I386,     0
+    .set MULTIBOOT2_HEADER_LENGTH, mboot2_end - mboot2
+    .set MULTIBOOT2_CHECKSUM,      -(MULTIBOOT2_HEADER_MAGIC + MULTIBOOT2_ARCH_I386 + MULTIBOOT2_HEADER_LENGTH)
+
+    .set MULTIBOOT2_TAG_END,         0
+    .set MULTIBOOT2_TAG_FRAMEBUFFER, 5
+    .set MULTIBOOT2_TAG_OPTIONAL,    1
+
+    .4byte MULTIBOOT2_HEADER_MAGIC
+    .4byte MULTIBOOT2_ARCH_I386
+    .4byte MULTIBOOT2_HEADER_LENGTH
+    .4byte MULTIBOOT2_CHECKSUM
+
+    # Ask for a linear framebuffer without preferring any mode.
+    .align 8
+    .2byte MULTIBOOT2_TAG_FRAMEBUFFER
+    .2byte MULTIBOOT2_TAG_OPTIONAL
+    .4byte 20
+    .4byte 0, 0, 0 # width, height, depth
+
+    .align 8
+    .2byte MULTIBOOT2_TAG_END
+    .2byte 0
+    .4byte 8
+mboot2_end:
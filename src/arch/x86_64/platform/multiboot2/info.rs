@@ -0,0 +1,330 @@
+//! Parsing of the Multiboot2 boot information.
+//!
+//! See the [Multiboot2 specification](https://www.gnu.org/software/grub/manual/multiboot2/multiboot.html).
+
+use core::ffi::CStr;
+use core::ops::Range;
+use core::{fmt, mem, slice};
+
+const TAG_END: u32 = 0;
+const TAG_CMDLINE: u32 = 1;
+const TAG_MODULE: u32 = 3;
+const TAG_MMAP: u32 = 6;
+const TAG_FRAMEBUFFER: u32 = 8;
+const TAG_ACPI_OLD: u32 = 14;
+const TAG_ACPI_NEW: u32 = 15;
+
+/// The Multiboot2 boot information.
+#[derive(Clone, Copy)]
+pub struct BootInformation {
+	bytes: &'static [u8],
+}
+
+impl BootInformation {
+	/// Creates the boot information from the pointer passed by the boot loader.
+	///
+	/// # Safety
+	///
+	/// `ptr` must point to valid Multiboot2 boot information that is never modified.
+	pub unsafe fn from_ptr(ptr: *const u8) -> Self {
+		let total_size = unsafe { ptr.cast::<u32>().read() };
+		let bytes = unsafe { slice::from_raw_parts(ptr, total_size.try_into().unwrap()) };
+		Self { bytes }
+	}
+
+	/// Returns the physical address range of the boot information.
+	pub fn range(&self) -> Range<usize> {
+		let Range { start, end } = self.bytes.as_ptr_range();
+		start.addr()..end.addr()
+	}
+
+	fn tags(&self) -> Tags {
+		Tags {
+			bytes: self.bytes.get(8..).unwrap_or_default(),
+		}
+	}
+
+	fn tag(&self, typ: u32) -> Option<&'static [u8]> {
+		self.tags()
+			.find(|(tag_typ, _data)| *tag_typ == typ)
+			.map(|(_typ, data)| data)
+	}
+
+	/// Returns the command line.
+	pub fn command_line(&self) -> Option<&'static str> {
+		c_str(self.tag(TAG_CMDLINE)?)
+	}
+
+	/// Returns the boot modules.
+	pub fn modules(&self) -> impl Iterator<Item = Module> {
+		self.tags()
+			.filter(|(typ, _data)| *typ == TAG_MODULE)
+			.filter_map(|(_typ, data)| {
+				Some(Module {
+					start: read_u32(data, 0)?.into(),
+					end: read_u32(data, 4)?.into(),
+					string: c_str(data.get(8..)?).unwrap_or_default(),
+				})
+			})
+	}
+
+	/// Returns the memory map.
+	pub fn memory_map(&self) -> Option<impl Iterator<Item = MemoryMapEntry>> {
+		let data = self.tag(TAG_MMAP)?;
+		let entry_size = usize::try_from(read_u32(data, 0)?).unwrap();
+		if entry_size == 0 {
+			return None;
+		}
+		let entries = data.get(8..)?.chunks_exact(entry_size).map_while(|entry| {
+			Some(MemoryMapEntry {
+				base_address: read_u64(entry, 0)?,
+				length: read_u64(entry, 8)?,
+				typ: read_u32(entry, 16)?,
+			})
+		});
+		Some(entries)
+	}
+
+	/// Returns the copy of the ACPI RSDP, preferring the ACPI 2.0 RSDP.
+	pub fn rsdp(&self) -> Option<&'static [u8]> {
+		self.tag(TAG_ACPI_NEW).or_else(|| self.tag(TAG_ACPI_OLD))
+	}
+
+	/// Returns the framebuffer information.
+	pub fn framebuffer(&self) -> Option<Framebuffer> {
+		let data = self.tag(TAG_FRAMEBUFFER)?;
+		Some(Framebuffer {
+			address: read_u64(data, 0)?,
+			pitch: read_u32(data, 8)?,
+			width: read_u32(data, 12)?,
+			height: read_u32(data, 16)?,
+			bpp: *data.get(20)?,
+			typ: *data.get(21)?,
+			rgb: match data.get(24..30) {
+				Some(
+					&[
+						red_pos,
+						red_size,
+						green_pos,
+						green_size,
+						blue_pos,
+						blue_size,
+					],
+				) => [
+					(red_pos, red_size),
+					(green_pos, green_size),
+					(blue_pos, blue_size),
+				],
+				_ => [(0, 0); 3],
+			},
+		})
+	}
+}
+
+/// An iterator over the tags of the boot information, yielding their type and data.
+///
+/// Iteration ends at the end tag or at the first malformed tag.
+struct Tags {
+	bytes: &'static [u8],
+}
+
+impl Iterator for Tags {
+	type Item = (u32, &'static [u8]);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let bytes = mem::take(&mut self.bytes);
+
+		let typ = read_u32(bytes, 0)?;
+		if typ == TAG_END {
+			return None;
+		}
+
+		let size = usize::try_from(read_u32(bytes, 4)?).unwrap();
+		let data = bytes.get(8..size)?;
+		self.bytes = bytes.get(size.next_multiple_of(8)..).unwrap_or_default();
+		Some((typ, data))
+	}
+}
+
+/// A boot module.
+pub struct Module {
+	pub start: u64,
+	pub end: u64,
+	pub string: &'static str,
+}
+
+/// An entry of the memory map.
+pub struct MemoryMapEntry {
+	pub base_address: u64,
+	pub length: u64,
+	pub typ: u32,
+}
+
+impl MemoryMapEntry {
+	pub fn range(&self) -> Range<u64> {
+		self.base_address..self.base_address + self.length
+	}
+
+	pub fn is_available(&self) -> bool {
+		self.typ == 1
+	}
+}
+
+impl fmt::Display for MemoryMapEntry {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let typ = match self.typ {
+			1 => "available",
+			3 => "ACPI reclaimable",
+			4 => "ACPI NVS",
+			5 => "defective",
+			_ => "reserved",
+		};
+		write!(
+			f,
+			"[{:#x} - {:#x}] {typ}",
+			self.base_address,
+			self.base_address + self.length
+		)
+	}
+}
+
+/// The framebuffer information.
+pub struct Framebuffer {
+	pub address: u64,
+	pub pitch: u32,
+	pub width: u32,
+	pub height: u32,
+	pub bpp: u8,
+	/// 0 for indexed color, 1 for direct RGB color, and 2 for EGA text.
+	pub typ: u8,
+	/// The position and size in bits of the red, green, and blue fields for direct RGB color.
+	pub rgb: [(u8, u8); 3],
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+	Some(u32::from_le_bytes(*bytes.get(offset..)?.first_chunk()?))
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> Option<u64> {
+	Some(u64::from_le_bytes(*bytes.get(offset..)?.first_chunk()?))
+}
+
+fn c_str(bytes: &'static [u8]) -> Option<&'static str> {
+	CStr::from_bytes_until_nul(bytes).ok()?.to_str().ok()
+}
+
+#[cfg(all(test, not(target_os = "none")))]
+mod tests {
+	use alloc::vec::Vec;
+
+	use super::*;
+
+	fn tag(info: &mut Vec<u8>, typ: u32, data: &[u8]) {
+		info.extend(typ.to_le_bytes());
+		info.extend(u32::try_from(8 + data.len()).unwrap().to_le_bytes());
+		info.extend(data);
+		info.resize(info.len().next_multiple_of(8), 0);
+	}
+
+	fn boot_information(tags: impl FnOnce(&mut Vec<u8>)) -> BootInformation {
+		let mut info = Vec::from([0; 8]);
+		tags(&mut info);
+		tag(&mut info, TAG_END, &[]);
+		let total_size = u32::try_from(info.len()).unwrap();
+		info[..4].copy_from_slice(&total_size.to_le_bytes());
+		BootInformation { bytes: info.leak() }
+	}
+
+	fn memory_map_entry(base_address: u64, length: u64, typ: u32) -> [u8; 24] {
+		let mut entry = [0; 24];
+		entry[..8].copy_from_slice(&base_address.to_le_bytes());
+		entry[8..16].copy_from_slice(&length.to_le_bytes());
+		entry[16..20].copy_from_slice(&typ.to_le_bytes());
+		entry
+	}
+
+	#[test]
+	fn tags() {
+		let info = boot_information(|info| {
+			tag(info, TAG_CMDLINE, b"-freq 1000\0");
+
+			let mut module = Vec::new();
+			module.extend(0x10_0000u32.to_le_bytes());
+			module.extend(0x20_0000u32.to_le_bytes());
+			module.extend(b"hermit-app\0");
+			tag(info, TAG_MODULE, &module);
+
+			let mut mmap = Vec::new();
+			mmap.extend(24u32.to_le_bytes());
+			mmap.extend(0u32.to_le_bytes());
+			mmap.extend(memory_map_entry(0, 0x9fc00, 1));
+			mmap.extend(memory_map_entry(0xf0000, 0x10000, 2));
+			tag(info, TAG_MMAP, &mmap);
+
+			tag(info, TAG_ACPI_OLD, b"RSD PTR ");
+		});
+
+		assert_eq!(info.command_line(), Some("-freq 1000"));
+
+		let modules = info.modules().collect::<Vec<_>>();
+		assert_eq!(modules.len(), 1);
+		assert_eq!(modules[0].start..modules[0].end, 0x10_0000..0x20_0000);
+		assert_eq!(modules[0].string, "hermit-app");
+
+		let memory_map = info.memory_map().unwrap().collect::<Vec<_>>();
+		assert_eq!(memory_map.len(), 2);
+		assert_eq!(memory_map[0].range(), 0..0x9fc00);
+		assert!(memory_map[0].is_available());
+		assert_eq!(memory_map[1].range(), 0xf0000..0x100000);
+		assert!(!memory_map[1].is_available());
+
+		assert_eq!(info.rsdp(), Some(&b"RSD PTR "[..]));
+		assert!(info.framebuffer().is_none());
+	}
+
+	#[test]
+	fn malformed_tags() {
+		// A tag that is smaller than its header
+		let info = boot_information(|info| {
+			info.extend(TAG_CMDLINE.to_le_bytes());
+			info.extend(4u32.to_le_bytes());
+		});
+		assert_eq!(info.tags().count(), 0);
+
+		// A tag that extends past the total size
+		let info = boot_information(|info| {
+			tag(info, TAG_CMDLINE, b"-freq 1000\0");
+			info.extend(TAG_MODULE.to_le_bytes());
+			info.extend(0x100u32.to_le_bytes());
+		});
+		assert_eq!(info.tags().count(), 1);
+		assert_eq!(info.modules().count(), 0);
+
+		// Truncated module and framebuffer tags
+		let info = boot_information(|info| {
+			tag(info, TAG_MODULE, &[0; 4]);
+			tag(info, TAG_FRAMEBUFFER, &[0; 20]);
+		});
+		assert_eq!(info.modules().count(), 0);
+		assert!(info.framebuffer().is_none());
+	}
+
+	#[test]
+	fn malformed_memory_map() {
+		let info = boot_information(|info| tag(info, TAG_MMAP, &[0; 8]));
+		assert!(info.memory_map().is_none());
+
+		let info = boot_information(|info| tag(info, TAG_MMAP, &[0; 4]));
+		assert!(info.memory_map().is_none());
+
+		// Entries that are too small to hold the type
+		let info = boot_information(|info| {
+			let mut mmap = Vec::new();
+			mmap.extend(16u32.to_le_bytes());
+			mmap.extend(0u32.to_le_bytes());
+			mmap.extend([0; 32]);
+			tag(info, TAG_MMAP, &mmap);
+		});
+		assert_eq!(info.memory_map().unwrap().count(), 0);
+	}
+}
@@ -0,0 +1,253 @@
+mod info;
+
+use core::ptr::write_bytes;
+use core::sync::atomic::{AtomicPtr, Ordering};
+use core::{ptr, slice};
+
+use align_address::Align;
+use hermit_entry::boot_info::{
+	BootInfo, DeviceTreeAddress, HardwareInfo, PlatformInfo, SerialPortBase,
+};
+use hermit_entry::elf::LoadedKernel;
+use log::{info, warn};
+use x86_64::structures::paging::{PageSize, Size2MiB};
+
+use self::info::{BootInformation, Framebuffer};
+use crate::arch::x86_64::physicalmem::PhysAlloc;
+use crate::arch::x86_64::{KERNEL_STACK_SIZE, SERIAL_IO_PORT, page_tables};
+use crate::fdt::Fdt;
+use crate::framebuffer::{self, PixelFormat};
+use crate::reserved_memory::{Reservation, ReservedMemory};
+use crate::{BootInfoExt, random};
+
+#[allow(bad_asm_style)]
+mod entry {
+	core::arch::global_asm!(
+		include_str!("entry.s"),
+		include_str!("../start32.s"),
+		rust_start = sym super::rust_start,
+		stack = sym crate::arch::x86_64::stack::STACK,
+		stack_top_offset = const crate::arch::x86_64::stack::Stack::top_offset(),
+		level_4_table = sym crate::arch::x86_64::page_tables::LEVEL_4_TABLE,
+		gdt_ptr = sym crate::arch::x86_64::gdt::GDT_PTR,
+		kernel_code_selector = const crate::arch::x86_64::gdt::Gdt::kernel_code_selector().0,
+		kernel_data_selector = const crate::arch::x86_64::gdt::Gdt::kernel_data_selector().0,
+	);
+}
+
+static MB2_INFO: AtomicPtr<u8> = AtomicPtr::new(ptr::null_mut());
+
+fn boot_information() -> BootInformation {
+	let mb2_info = MB2_INFO.load(Ordering::Relaxed);
+	assert!(!mb2_info.is_null(), "Could not find Multiboot2 information");
+	unsafe { BootInformation::from_ptr(mb2_info) }
+}
+
+unsafe extern "C" fn rust_start(mb2_info: *mut u8) -> ! {
+	crate::log::init();
+	MB2_INFO.store(mb2_info, Ordering::Relaxed);
+
+	let boot_info = boot_information();
+	let memory_map = || {
+		boot_info
+			.memory_map()
+			.expect("Could not find a memory map in the Multiboot2 information")
+	};
+
+	// Memory after the loader, the boot information, and all modules is available for the physical memory manager.
+	let highest_address = boot_info
+		.modules()
+		.map(|module| usize::try_from(module.end).unwrap())
+		.chain([elf_symbols::executable_end().addr(), boot_info.range().end])
+		.max()
+		.unwrap()
+		.align_up(Size2MiB::SIZE as usize);
+	let free_end = memory_map()
+		.filter(|entry| entry.is_available())
+		.map(|entry| entry.range())
+		.find(|range| range.contains(&(highest_address as u64)))
+		.map_or(highest_address, |range| range.end as usize);
+	info!("Intializing PhysAlloc with {highest_address:#x}..{free_end:#x}");
+	PhysAlloc::init(highest_address, free_end);
+
+	let max_phys_addr = memory_map()
+		.filter(|entry| entry.is_available())
+		.map(|entry| entry.range().end)
+		.max()
+		.unwrap();
+
+	unsafe {
+		page_tables::init(max_phys_addr.try_into().unwrap());
+	}
+
+	if let Some(framebuffer) = framebuffer(&boot_info) {
+		crate::arch::x86_64::init_framebuffer_console(framebuffer);
+	}
+
+	unsafe {
+		crate::os::loader_main();
+	}
+}
+
+/// Returns the framebuffer from the Multiboot2 information, if its format is supported.
+fn framebuffer(boot_info: &BootInformation) -> Option<framebuffer::Framebuffer> {
+	let Framebuffer {
+		address,
+		pitch,
+		width,
+		height,
+		bpp,
+		typ,
+		rgb,
+	} = boot_info.framebuffer()?;
+	info!(
+		"Found framebuffer at {address:#x}: {width}x{height}, {bpp} bpp, pitch = {pitch}, type = {typ}"
+	);
+
+	let Some(format) = PixelFormat::from_rgb(bpp, rgb).filter(|_| typ == 1) else {
+		warn!("Unsupported framebuffer format: type = {typ}, rgb = {rgb:?}");
+		return None;
+	};
+
+	Some(framebuffer::Framebuffer {
+		address,
+		width,
+		height,
+		stride: pitch,
+		format,
+	})
+}
+
+pub fn find_kernel() -> &'static [u8] {
+	let boot_info = boot_information();
+	info!(
+		"Found Multiboot2 information at {:#x}",
+		boot_info.range().start
+	);
+
+	let module = boot_info
+		.modules()
+		.next()
+		.expect("Could not find a single module in the Multiboot2 information");
+	info!(
+		"Found an ELF module at [{:#x} - {:#x}]",
+		module.start, module.end
+	);
+
+	let start = usize::try_from(module.start).unwrap();
+	let len = usize::try_from(module.end - module.start).unwrap();
+	unsafe { slice::from_raw_parts(ptr::with_exposed_provenance(start), len) }
+}
+
+pub fn command_line() -> Option<&'static str> {
+	boot_information().command_line()
+}
+
+pub unsafe fn boot_kernel(
+	kernel_info: LoadedKernel,
+	bootargs: Option<&'static str>,
+	app_sha256: [u8; 32],
+) -> ! {
+	let LoadedKernel {
+		load_info,
+		entry_point,
+	} = kernel_info;
+
+	let boot_info = boot_information();
+
+	// The Multiboot2 information may lie anywhere, so we allocate the stack.
+	let stack = unsafe { crate::arch::get_memory(KERNEL_STACK_SIZE) };
+	let stack = ptr::with_exposed_provenance_mut::<u8>(stack.try_into().unwrap());
+	unsafe {
+		write_bytes(stack, 0, KERNEL_STACK_SIZE.try_into().unwrap());
+	}
+
+	let mut fdt = Fdt::new("multiboot2").unwrap();
+
+	let boot_info_range = boot_info.range();
+	fdt = fdt
+		.reserved_memory(ReservedMemory::loader())
+		.unwrap()
+		.reserved_memory(ReservedMemory::new(
+			boot_info_range.start as u64..boot_info_range.end as u64,
+			"multiboot2-info",
+			Reservation::Reclaimable,
+		))
+		.unwrap()
+		.reserved_memory(ReservedMemory::reclaimable(find_kernel(), "initrd"))
+		.unwrap();
+
+	let memory_map = boot_info
+		.memory_map()
+		.expect("Could not find a memory map in the Multiboot2 information");
+	for entry in memory_map {
+		info!("Multiboot2 memory region: {entry}");
+		if entry.is_available() {
+			fdt = fdt.memory(entry.range()).unwrap();
+		}
+	}
+
+	let phys_addr_range = {
+		let available = || {
+			boot_info
+				.memory_map()
+				.unwrap()
+				.filter(|entry| entry.is_available())
+				.map(|entry| entry.range())
+		};
+		let start = available()
+			.map(|range| range.start)
+			.find(|start| *start != 0)
+			.unwrap();
+		let end = available().map(|range| range.end).max().unwrap();
+		start..end
+	};
+
+	// The first module is the kernel. All others are forwarded.
+	for module in boot_info.modules().skip(1) {
+		info!(
+			"Forwarding module {:?} at [{:#x} - {:#x}]",
+			module.string, module.start, module.end
+		);
+		fdt = fdt.module(module.start..module.end, module.string).unwrap();
+	}
+
+	if let Some(rsdp) = boot_info.rsdp() {
+		let rsdp = u64::try_from(rsdp.as_ptr().expose_provenance()).unwrap();
+		info!("Found ACPI RSDP at {rsdp:#x}");
+		fdt = fdt.rsdp(rsdp).unwrap();
+	}
+
+	if let Some(framebuffer) = framebuffer(&boot_info) {
+		fdt = fdt.framebuffer(framebuffer).unwrap();
+	}
+
+	if let Some(bootargs) = bootargs {
+		fdt = fdt.bootargs(bootargs.into()).unwrap();
+	}
+
+	if let Some(rng_seed) = random::seed() {
+		fdt = fdt.rng_seed(&rng_seed).unwrap();
+	}
+
+	fdt = fdt.app_sha256(app_sha256).unwrap();
+
+	let fdt = fdt.finish().unwrap();
+	let device_tree =
+		DeviceTreeAddress::new(u64::try_from(fdt.leak().as_ptr().expose_provenance()).unwrap());
+
+	let boot_info = BootInfo {
+		hardware_info: HardwareInfo {
+			phys_addr_range,
+			serial_port_base: SerialPortBase::new(SERIAL_IO_PORT),
+			device_tree,
+		},
+		load_info,
+		platform_info: PlatformInfo::Fdt,
+	};
+
+	let entry = ptr::with_exposed_provenance(entry_point.try_into().unwrap());
+	let raw_boot_info = boot_info.write();
+
+	unsafe { crate::arch::x86_64::enter_kernel(stack, entry, raw_boot_info) }
+}
@@ -0,0 +1,19 @@
+.code32
+
+# The PVH ELF note tells the hypervisor where to enter the loader in 32-bit protected mode.
+# See https://xenbits.xen.org/docs/unstable/misc/pvh.html
+.section .note.Xen, "a", @note
+.align 4
+    .set XEN_ELFNOTE_PHYS32_ENTRY, 18
+
+    .4byte 2f - 1f # name size
+    .4byte 4f - 3f # descriptor size
+    .4byte XEN_ELFNOTE_PHYS32_ENTRY
+1:
+    .asciz "Xen"
+2:
+    .align 4
+3:
+    .4byte _start
+4:
+    .align 4
@@ -0,0 +1,220 @@
+mod start_info;
+
+use core::ptr::write_bytes;
+use core::sync::atomic::{AtomicPtr, Ordering};
+use core::{mem, ptr, slice};
+
+use align_address::Align;
+use hermit_entry::boot_info::{
+	BootInfo, DeviceTreeAddress, HardwareInfo, PlatformInfo, SerialPortBase,
+};
+use hermit_entry::elf::LoadedKernel;
+use log::info;
+use x86_64::structures::paging::{PageSize, Size2MiB};
+
+use self::start_info::StartInfo;
+use crate::arch::x86_64::physicalmem::PhysAlloc;
+use crate::arch::x86_64::{KERNEL_STACK_SIZE, SERIAL_IO_PORT, page_tables};
+use crate::fdt::Fdt;
+use crate::reserved_memory::{Reservation, ReservedMemory};
+use crate::{BootInfoExt, random};
+
+#[allow(bad_asm_style)]
+mod entry {
+	core::arch::global_asm!(
+		include_str!("entry.s"),
+		include_str!("../start32.s"),
+		rust_start = sym super::rust_start,
+		stack = sym crate::arch::x86_64::stack::STACK,
+		stack_top_offset = const crate::arch::x86_64::stack::Stack::top_offset(),
+		level_4_table = sym crate::arch::x86_64::page_tables::LEVEL_4_TABLE,
+		gdt_ptr = sym crate::arch::x86_64::gdt::GDT_PTR,
+		kernel_code_selector = const crate::arch::x86_64::gdt::Gdt::kernel_code_selector().0,
+		kernel_data_selector = const crate::arch::x86_64::gdt::Gdt::kernel_data_selector().0,
+	);
+}
+
+static START_INFO: AtomicPtr<StartInfo> = AtomicPtr::new(ptr::null_mut());
+
+fn start_info() -> &'static StartInfo {
+	let start_info = START_INFO.load(Ordering::Relaxed);
+	assert!(!start_info.is_null(), "Could not find PVH start info");
+	unsafe { StartInfo::from_ptr(start_info) }
+}
+
+unsafe extern "C" fn rust_start(start_info: *mut StartInfo) -> ! {
+	crate::log::init();
+	START_INFO.store(start_info, Ordering::Relaxed);
+
+	let start_info = self::start_info();
+	let memory_map = start_info
+		.memory_map()
+		.expect("Could not find a memory map in the PVH start info");
+
+	// Memory after the loader, the start info, and all modules is available for the physical memory manager.
+	let highest_address = start_info
+		.modules()
+		.iter()
+		.map(|module| usize::try_from(module.range().end).unwrap())
+		.chain([elf_symbols::executable_end().addr(), start_info.end()])
+		.max()
+		.unwrap()
+		.align_up(Size2MiB::SIZE as usize);
+	let free_end = memory_map
+		.iter()
+		.filter(|entry| entry.is_ram())
+		.map(|entry| entry.range())
+		.find(|range| range.contains(&(highest_address as u64)))
+		.map_or(highest_address, |range| range.end as usize);
+	info!("Intializing PhysAlloc with {highest_address:#x}..{free_end:#x}");
+	PhysAlloc::init(highest_address, free_end);
+
+	let max_phys_addr = memory_map
+		.iter()
+		.filter(|entry| entry.is_ram())
+		.map(|entry| entry.range().end)
+		.max()
+		.unwrap();
+
+	unsafe {
+		page_tables::init(max_phys_addr.try_into().unwrap());
+	}
+
+	unsafe {
+		crate::os::loader_main();
+	}
+}
+
+pub fn find_kernel() -> &'static [u8] {
+	let start_info = start_info();
+	info!("Found PVH start info at {start_info:p}");
+
+	let module = start_info
+		.modules()
+		.first()
+		.expect("Could not find a single module in the PVH start info");
+	let range = module.range();
+	info!(
+		"Found an ELF module at [{:#x} - {:#x}]",
+		range.start, range.end
+	);
+
+	let start = usize::try_from(module.paddr).unwrap();
+	let len = usize::try_from(module.size).unwrap();
+	unsafe { slice::from_raw_parts(ptr::with_exposed_provenance(start), len) }
+}
+
+pub fn command_line() -> Option<&'static str> {
+	start_info().command_line()
+}
+
+pub unsafe fn boot_kernel(
+	kernel_info: LoadedKernel,
+	bootargs: Option<&'static str>,
+	app_sha256: [u8; 32],
+) -> ! {
+	let LoadedKernel {
+		load_info,
+		entry_point,
+	} = kernel_info;
+
+	let start_info = start_info();
+
+	// The PVH start info may lie anywhere, so we allocate the stack.
+	let stack = unsafe { crate::arch::get_memory(KERNEL_STACK_SIZE) };
+	let stack = ptr::with_exposed_provenance_mut::<u8>(stack.try_into().unwrap());
+	unsafe {
+		write_bytes(stack, 0, KERNEL_STACK_SIZE.try_into().unwrap());
+	}
+
+	let mut fdt = Fdt::new("pvh").unwrap();
+
+	let start_info_addr = ptr::from_ref(start_info).expose_provenance() as u64;
+	let start_info_end = start_info_addr + mem::size_of::<StartInfo>() as u64;
+	fdt = fdt
+		.reserved_memory(ReservedMemory::loader())
+		.unwrap()
+		.reserved_memory(ReservedMemory::new(
+			start_info_addr..start_info_end,
+			"start-info",
+			Reservation::Reclaimable,
+		))
+		.unwrap()
+		.reserved_memory(ReservedMemory::reclaimable(find_kernel(), "initrd"))
+		.unwrap();
+	if let Some(command_line) = start_info.command_line() {
+		let command_line =
+			unsafe { slice::from_raw_parts(command_line.as_ptr(), command_line.len() + 1) };
+		fdt = fdt
+			.reserved_memory(ReservedMemory::reclaimable(command_line, "cmdline"))
+			.unwrap();
+	}
+
+	let memory_map = start_info.memory_map().unwrap();
+	for entry in memory_map {
+		info!("PVH memory region: {entry}");
+		if entry.is_ram() {
+			fdt = fdt.memory(entry.range()).unwrap();
+		}
+	}
+
+	let phys_addr_range = {
+		let ram = || {
+			memory_map
+				.iter()
+				.filter(|entry| entry.is_ram())
+				.map(|entry| entry.range())
+		};
+		let start = ram()
+			.map(|range| range.start)
+			.find(|start| *start != 0)
+			.unwrap();
+		let end = ram().map(|range| range.end).max().unwrap();
+		start..end
+	};
+
+	// The first module is the kernel. All others are forwarded.
+	for module in start_info.modules().iter().skip(1) {
+		let string = module.command_line().unwrap_or_default();
+		let range = module.range();
+		info!(
+			"Forwarding module {string:?} at [{:#x} - {:#x}]",
+			range.start, range.end
+		);
+		fdt = fdt.module(range, string).unwrap();
+	}
+
+	if let Some(rsdp) = start_info.rsdp() {
+		info!("Found ACPI RSDP at {rsdp:#x}");
+		fdt = fdt.rsdp(rsdp).unwrap();
+	}
+
+	if let Some(bootargs) = bootargs {
+		fdt = fdt.bootargs(bootargs.into()).unwrap();
+	}
+
+	if let Some(rng_seed) = random::seed() {
+		fdt = fdt.rng_seed(&rng_seed).unwrap();
+	}
+
+	fdt = fdt.app_sha256(app_sha256).unwrap();
+
+	let fdt = fdt.finish().unwrap();
+	let device_tree =
+		DeviceTreeAddress::new(u64::try_from(fdt.leak().as_ptr().expose_provenance()).unwrap());
+
+	let boot_info = BootInfo {
+		hardware_info: HardwareInfo {
+			phys_addr_range,
+			serial_port_base: SerialPortBase::new(SERIAL_IO_PORT),
+			device_tree,
+		},
+		load_info,
+		platform_info: PlatformInfo::Fdt,
+	};
+
+	let entry = ptr::with_exposed_provenance(entry_point.try_into().unwrap());
+	let raw_boot_info = boot_info.write();
+
+	unsafe { crate::arch::x86_64::enter_kernel(stack, entry, raw_boot_info) }
+}
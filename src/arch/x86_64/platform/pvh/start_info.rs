@@ -0,0 +1,164 @@
+//! The PVH start info.
+//!
+//! See [`xen/include/public/arch-x86/hvm/start_info.h`](https://xenbits.xen.org/gitweb/?p=xen.git;a=blob;f=xen/include/public/arch-x86/hvm/start_info.h).
+
+use core::ffi::{CStr, c_char};
+use core::ops::Range;
+use core::{fmt, iter, mem, ptr, slice};
+
+const XEN_HVM_START_MAGIC_VALUE: u32 = 0x336e_c578;
+
+/// `struct hvm_start_info`.
+#[repr(C)]
+pub struct StartInfo {
+	magic: u32,
+	version: u32,
+	flags: u32,
+	nr_modules: u32,
+	modlist_paddr: u64,
+	cmdline_paddr: u64,
+	rsdp_paddr: u64,
+	// The following fields are only present since version 1.
+	memmap_paddr: u64,
+	memmap_entries: u32,
+	reserved: u32,
+}
+
+/// `struct hvm_modlist_entry`.
+#[repr(C)]
+pub struct ModlistEntry {
+	pub paddr: u64,
+	pub size: u64,
+	cmdline_paddr: u64,
+	reserved: u64,
+}
+
+/// `struct hvm_memmap_table_entry`.
+#[repr(C)]
+pub struct MemmapTableEntry {
+	pub addr: u64,
+	pub size: u64,
+	pub typ: u32,
+	reserved: u32,
+}
+
+impl StartInfo {
+	/// Returns the start info at `ptr`.
+	///
+	/// # Safety
+	///
+	/// `ptr` must point to a valid start info that is never modified.
+	pub unsafe fn from_ptr(ptr: *const Self) -> &'static Self {
+		let start_info = unsafe { &*ptr };
+		assert_eq!(
+			start_info.magic, XEN_HVM_START_MAGIC_VALUE,
+			"Invalid PVH start info magic"
+		);
+		start_info
+	}
+
+	/// Returns the command line.
+	pub fn command_line(&self) -> Option<&'static str> {
+		unsafe { c_str(self.cmdline_paddr) }
+	}
+
+	/// Returns the boot modules.
+	pub fn modules(&self) -> &'static [ModlistEntry] {
+		unsafe { table(self.modlist_paddr, self.nr_modules) }
+	}
+
+	/// Returns the memory map.
+	pub fn memory_map(&self) -> Option<&'static [MemmapTableEntry]> {
+		if self.version < 1 {
+			return None;
+		}
+
+		Some(unsafe { table(self.memmap_paddr, self.memmap_entries) })
+	}
+
+	/// Returns the end of the start info and of the module list, memory map, and command lines it refers to.
+	pub fn end(&self) -> usize {
+		let start_info = ptr::from_ref(self).addr() + mem::size_of::<Self>();
+		let modules = self.modules().as_ptr_range().end.addr();
+		let memory_map = self
+			.memory_map()
+			.map_or(0, |memory_map| memory_map.as_ptr_range().end.addr());
+		let command_lines = iter::once(self.cmdline_paddr)
+			.chain(self.modules().iter().map(|module| module.cmdline_paddr))
+			.map(|paddr| unsafe { c_str_end(paddr) });
+
+		[start_info, modules, memory_map]
+			.into_iter()
+			.chain(command_lines)
+			.max()
+			.unwrap()
+	}
+
+	/// Returns the physical address of the ACPI RSDP.
+	pub fn rsdp(&self) -> Option<u64> {
+		(self.rsdp_paddr != 0).then_some(self.rsdp_paddr)
+	}
+}
+
+impl ModlistEntry {
+	pub fn range(&self) -> Range<u64> {
+		self.paddr..self.paddr + self.size
+	}
+
+	/// Returns the module's command line.
+	pub fn command_line(&self) -> Option<&'static str> {
+		unsafe { c_str(self.cmdline_paddr) }
+	}
+}
+
+impl MemmapTableEntry {
+	pub fn range(&self) -> Range<u64> {
+		self.addr..self.addr + self.size
+	}
+
+	pub fn is_ram(&self) -> bool {
+		self.typ == 1
+	}
+}
+
+impl fmt::Display for MemmapTableEntry {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let typ = match self.typ {
+			1 => "RAM",
+			2 => "reserved",
+			3 => "ACPI",
+			4 => "ACPI NVS",
+			5 => "unusable",
+			_ => "unknown",
+		};
+		write!(f, "[{:#x} - {:#x}] {typ}", self.addr, self.addr + self.size)
+	}
+}
+
+unsafe fn c_str(paddr: u64) -> Option<&'static str> {
+	if paddr == 0 {
+		return None;
+	}
+
+	let ptr = ptr::with_exposed_provenance::<c_char>(paddr.try_into().unwrap());
+	unsafe { CStr::from_ptr(ptr) }.to_str().ok()
+}
+
+/// Returns the end of the null-terminated string at `paddr`, or 0 if there is none.
+unsafe fn c_str_end(paddr: u64) -> usize {
+	if paddr == 0 {
+		return 0;
+	}
+
+	let ptr = ptr::with_exposed_provenance::<c_char>(paddr.try_into().unwrap());
+	ptr.addr() + unsafe { CStr::from_ptr(ptr) }.count_bytes() + 1
+}
+
+unsafe fn table<T>(paddr: u64, len: u32) -> &'static [T] {
+	if paddr == 0 || len == 0 {
+		return &[];
+	}
+
+	let ptr = ptr::with_exposed_provenance::<T>(paddr.try_into().unwrap());
+	unsafe { slice::from_raw_parts(ptr, len.try_into().unwrap()) }
+}
@@ -0,0 +1,180 @@
+//! Layered bootargs.
+//!
+//! Bootargs are merged from the following layers in order:
+//!
+//! 1. the built-in defaults from `LOADER_BOOTARGS` at build time,
+//! 2. `bootargs` from `loader.conf` or a bootargs file (UEFI only),
+//! 3. the command line provided by the firmware or bootloader.
+//!
+//! Each layer is split into kernel and application arguments at the first `--`.
+//! With `LOADER_BOOTARGS_MODE=append` (default), each layer appends its kernel and application arguments to the previous ones.
+//! With `LOADER_BOOTARGS_MODE=override`, non-empty kernel or application arguments replace the previous ones.
+
+use alloc::string::String;
+
+use log::info;
+
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum Mode {
+	#[default]
+	Append,
+	Override,
+}
+
+impl Mode {
+	/// The mode from `LOADER_BOOTARGS_MODE`, which is validated at compile time.
+	const FROM_ENV: Self = match option_env!("LOADER_BOOTARGS_MODE") {
+		None => Self::Append,
+		Some(mode) => match mode.as_bytes() {
+			b"" | b"append" => Self::Append,
+			b"override" => Self::Override,
+			_ => panic!("Invalid LOADER_BOOTARGS_MODE, expected `append` or `override`"),
+		},
+	};
+}
+
+#[derive(Debug)]
+pub struct Bootargs {
+	mode: Mode,
+	kernel: String,
+	app: Option<String>,
+}
+
+impl Bootargs {
+	/// Creates bootargs from the built-in defaults.
+	pub fn new() -> Self {
+		let mut bootargs = Self {
+			mode: Mode::FROM_ENV,
+			kernel: String::new(),
+			app: None,
+		};
+
+		if let Some(defaults) = option_env!("LOADER_BOOTARGS") {
+			bootargs.layer("built-in defaults", defaults);
+		}
+
+		bootargs
+	}
+
+	/// Merges `args` from `source` into these bootargs.
+	pub fn layer(&mut self, source: &str, args: &str) {
+		info!("Bootargs from {source}: {args:?}");
+
+		let (kernel, app) = split_args(args);
+
+		if !kernel.is_empty() {
+			if self.mode == Mode::Override {
+				self.kernel.clear();
+			}
+			push_args(&mut self.kernel, kernel);
+		}
+
+		if let Some(app) = app.filter(|app| !app.is_empty()) {
+			match (&mut self.app, self.mode) {
+				(Some(prev), Mode::Append) => push_args(prev, app),
+				(prev, _) => *prev = Some(app.into()),
+			}
+		}
+	}
+
+	/// Returns the merged bootargs, if any.
+	pub fn finish(self) -> Option<String> {
+		let mut bootargs = self.kernel;
+		if let Some(app) = self.app {
+			push_args(&mut bootargs, "--");
+			push_args(&mut bootargs, &app);
+		}
+
+		info!("Bootargs: {bootargs:?}");
+		(!bootargs.is_empty()).then_some(bootargs)
+	}
+}
+
+/// Splits `args` into kernel and application arguments at the first `--`.
+fn split_args(args: &str) -> (&str, Option<&str>) {
+	let separator = args
+		.split_whitespace()
+		.find(|arg| *arg == "--")
+		.map(|arg| arg.as_ptr().addr() - args.as_ptr().addr());
+
+	match separator {
+		Some(separator) => (args[..separator].trim(), Some(args[separator + 2..].trim())),
+		None => (args.trim(), None),
+	}
+}
+
+fn push_args(args: &mut String, more: &str) {
+	if more.is_empty() {
+		return;
+	}
+	if !args.is_empty() {
+		args.push(' ');
+	}
+	args.push_str(more);
+}
+
+#[cfg(all(test, not(target_os = "none")))]
+mod tests {
+	use super::*;
+
+	fn bootargs(mode: Mode, layers: &[&str]) -> Option<String> {
+		let mut bootargs = Bootargs {
+			mode,
+			kernel: String::new(),
+			app: None,
+		};
+		for layer in layers {
+			bootargs.layer("test", layer);
+		}
+		bootargs.finish()
+	}
+
+	#[test]
+	fn split_args() {
+		assert_eq!(super::split_args(""), ("", None));
+		assert_eq!(super::split_args(" -freq 1000 "), ("-freq 1000", None));
+		assert_eq!(
+			super::split_args("-freq 1000 -- a"),
+			("-freq 1000", Some("a"))
+		);
+		assert_eq!(super::split_args("-- a -- b"), ("", Some("a -- b")));
+		assert_eq!(
+			super::split_args("--freq -- a--b"),
+			("--freq", Some("a--b"))
+		);
+	}
+
+	#[test]
+	fn append_mode() {
+		let layers = ["-freq 1000 -- a", "-ip 10.0.5.3", "-- b c"];
+		assert_eq!(
+			bootargs(Mode::Append, &layers).as_deref(),
+			Some("-freq 1000 -ip 10.0.5.3 -- a b c")
+		);
+	}
+
+	#[test]
+	fn override_mode() {
+		let layers = ["-freq 1000 -- a", "-ip 10.0.5.3", "-- b c"];
+		assert_eq!(
+			bootargs(Mode::Override, &layers).as_deref(),
+			Some("-ip 10.0.5.3 -- b c")
+		);
+	}
+
+	#[test]
+	fn empty_app_args() {
+		for mode in [Mode::Append, Mode::Override] {
+			assert_eq!(bootargs(mode, &[]), None);
+			assert_eq!(bootargs(mode, &["", "--"]), None);
+			assert_eq!(
+				bootargs(mode, &["-freq 1000 -- a", "--"]).as_deref(),
+				Some("-freq 1000 -- a")
+			);
+			assert_eq!(
+				bootargs(mode, &["-freq 1000 --"]).as_deref(),
+				Some("-freq 1000")
+			);
+		}
+	}
+}
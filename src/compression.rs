@@ -0,0 +1,594 @@
+//! Decompression of compressed Hermit application images.
+//!
+//! Compressed images are recognized by their magic bytes.
+//! All supported formats have to declare their decompressed size up front,
+//! so that the image can be decompressed into a single preallocated buffer.
+//! Checksums are verified where the format provides them.
+
+use alloc::boxed::Box;
+use core::{fmt, mem};
+
+use crc::{CRC_32_ISO_HDLC, Crc};
+use log::info;
+use lz4_flex::block::DecompressError as Lz4Error;
+use miniz_oxide::inflate::TINFLStatus;
+use miniz_oxide::inflate::core::DecompressorOxide;
+use miniz_oxide::inflate::core::inflate_flags::TINFL_FLAG_USING_NON_WRAPPING_OUTPUT_BUF;
+use ruzstd::decoding::FrameDecoder;
+use ruzstd::decoding::errors::FrameDecoderError;
+
+use crate::time::Instant;
+
+/// The largest Zstandard window that we are willing to decode.
+///
+/// This corresponds to `zstd -19` and is the largest window of the regular compression levels.
+const ZSTD_MAX_WINDOW_SIZE: u64 = 8 * 1024 * 1024;
+
+/// The size of the dictionary that LZ4 blocks may reference.
+const LZ4_DICT_SIZE: usize = 64 * 1024;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Compression {
+	Gzip,
+	Zstd,
+	Lz4,
+}
+
+impl Compression {
+	/// Detects the compression format from the magic bytes at the start of `image`.
+	pub fn detect(image: &[u8]) -> Option<Self> {
+		const GZIP_MAGIC: [u8; 3] = [0x1f, 0x8b, 0x08];
+		const ZSTD_MAGIC: [u8; 4] = 0xfd2fb528u32.to_le_bytes();
+		const LZ4_MAGIC: [u8; 4] = 0x184d2204u32.to_le_bytes();
+
+		if image.starts_with(&GZIP_MAGIC) {
+			Some(Self::Gzip)
+		} else if image.starts_with(&ZSTD_MAGIC) {
+			Some(Self::Zstd)
+		} else if image.starts_with(&LZ4_MAGIC) {
+			Some(Self::Lz4)
+		} else {
+			None
+		}
+	}
+
+	/// Returns the number of bytes of heap memory that the decompressor may allocate.
+	#[cfg_attr(target_os = "uefi", expect(unused))]
+	pub fn scratch_size(self) -> usize {
+		match self {
+			Self::Gzip => mem::size_of::<DecompressorOxide>().next_multiple_of(0x1000),
+			// The decode buffer grows by doubling beyond the window size and our heaps never free.
+			Self::Zstd => 4 * ZSTD_MAX_WINDOW_SIZE as usize + 0x10_0000,
+			Self::Lz4 => 0,
+		}
+	}
+
+	/// Returns the decompressed size as declared by `image`.
+	pub fn decompressed_size(self, image: &[u8]) -> Result<usize, Error> {
+		let size = match self {
+			Self::Gzip => {
+				let trailer = image.last_chunk::<4>().ok_or(Error::Truncated)?;
+				u32::from_le_bytes(*trailer).into()
+			}
+			Self::Zstd => {
+				let mut decoder = FrameDecoder::new();
+				decoder.init(image).map_err(Error::Zstd)?;
+				decoder.content_size()
+			}
+			Self::Lz4 => Lz4FrameHeader::parse(image)?
+				.content_size
+				.ok_or(Error::UnknownSize)?,
+		};
+
+		if size == 0 {
+			return Err(Error::UnknownSize);
+		}
+
+		Ok(size.try_into().unwrap())
+	}
+
+	/// Decompresses `image` into `out`, which has to have exactly the decompressed size.
+	pub fn decompress_into(self, image: &[u8], out: &mut [u8]) -> Result<(), Error> {
+		let len = image.len();
+		let expected = out.len();
+		info!("Decompressing {self} image (size = {len} B, decompressed size = {expected} B)");
+		let now = Instant::now();
+
+		let actual = match self {
+			Self::Gzip => inflate_gzip(image, out)?,
+			Self::Zstd => {
+				let mut decoder = FrameDecoder::new();
+				decoder.set_max_window_size(ZSTD_MAX_WINDOW_SIZE);
+				decoder.decode_all(image, out).map_err(Error::Zstd)?
+			}
+			Self::Lz4 => decompress_lz4_frame(image, out)?,
+		};
+
+		if actual != expected {
+			return Err(Error::SizeMismatch { expected, actual });
+		}
+
+		let elapsed = now.elapsed();
+		info!("Decompressed {self} image in {elapsed}");
+		Ok(())
+	}
+}
+
+impl fmt::Display for Compression {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let s = match self {
+			Self::Gzip => "gzip",
+			Self::Zstd => "zstd",
+			Self::Lz4 => "lz4",
+		};
+		f.write_str(s)
+	}
+}
+
+#[derive(Debug)]
+pub enum Error {
+	/// The image ended unexpectedly.
+	Truncated,
+	/// The image uses a format feature that we do not support.
+	Unsupported,
+	/// The image does not declare its decompressed size.
+	UnknownSize,
+	/// The decompressed size differs from the declared size.
+	SizeMismatch {
+		expected: usize,
+		actual: usize,
+	},
+	/// A checksum does not match the data it covers.
+	ChecksumMismatch,
+	Gzip(TINFLStatus),
+	Zstd(FrameDecoderError),
+	Lz4(Lz4Error),
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Truncated => f.write_str("the compressed image is truncated"),
+			Self::Unsupported => f.write_str("the compressed image uses an unsupported feature"),
+			Self::UnknownSize => {
+				f.write_str("the compressed image does not declare its decompressed size")
+			}
+			Self::SizeMismatch { expected, actual } => write!(
+				f,
+				"expected {expected} B of decompressed data, but got {actual} B"
+			),
+			Self::ChecksumMismatch => f.write_str("the compressed image is corrupt"),
+			Self::Gzip(status) => write!(f, "gzip: {status:?}"),
+			Self::Zstd(err) => write!(f, "zstd: {err}"),
+			Self::Lz4(err) => write!(f, "lz4: {err}"),
+		}
+	}
+}
+
+/// Decompresses a single gzip member as specified in [RFC 1952].
+///
+/// [RFC 1952]: https://www.rfc-editor.org/rfc/rfc1952
+fn inflate_gzip(image: &[u8], out: &mut [u8]) -> Result<usize, Error> {
+	const FHCRC: u8 = 1 << 1;
+	const FEXTRA: u8 = 1 << 2;
+	const FNAME: u8 = 1 << 3;
+	const FCOMMENT: u8 = 1 << 4;
+
+	fn skip_cstr(data: &[u8]) -> Result<&[u8], Error> {
+		let nul = data.iter().position(|b| *b == 0).ok_or(Error::Truncated)?;
+		Ok(&data[nul + 1..])
+	}
+
+	let (header, mut data) = image.split_first_chunk::<10>().ok_or(Error::Truncated)?;
+	let flags = header[3];
+
+	if flags & FEXTRA != 0 {
+		let (xlen, rest) = data.split_first_chunk::<2>().ok_or(Error::Truncated)?;
+		data = rest
+			.get(usize::from(u16::from_le_bytes(*xlen))..)
+			.ok_or(Error::Truncated)?;
+	}
+	if flags & FNAME != 0 {
+		data = skip_cstr(data)?;
+	}
+	if flags & FCOMMENT != 0 {
+		data = skip_cstr(data)?;
+	}
+	if flags & FHCRC != 0 {
+		data = data.get(2..).ok_or(Error::Truncated)?;
+	}
+
+	let mut decompressor = Box::<DecompressorOxide>::default();
+	let (status, read, written) = miniz_oxide::inflate::core::decompress(
+		&mut decompressor,
+		data,
+		out,
+		0,
+		TINFL_FLAG_USING_NON_WRAPPING_OUTPUT_BUF,
+	);
+
+	if status != TINFLStatus::Done {
+		return Err(Error::Gzip(status));
+	}
+
+	let trailer = data[read..].first_chunk::<8>().ok_or(Error::Truncated)?;
+	let (crc32, isize) = trailer.split_at(4);
+	let crc32 = u32::from_le_bytes(crc32.try_into().unwrap());
+	let isize = u32::from_le_bytes(isize.try_into().unwrap());
+
+	if isize != written as u32 {
+		return Err(Error::SizeMismatch {
+			expected: isize.try_into().unwrap(),
+			actual: written,
+		});
+	}
+
+	const CRC32: Crc<u32> = Crc::<u32>::new(&CRC_32_ISO_HDLC);
+	if CRC32.checksum(&out[..written]) != crc32 {
+		return Err(Error::ChecksumMismatch);
+	}
+
+	Ok(written)
+}
+
+/// The header of an [LZ4 frame].
+///
+/// [LZ4 frame]: https://github.com/lz4/lz4/blob/dev/doc/lz4_Frame_format.md
+struct Lz4FrameHeader {
+	independent_blocks: bool,
+	block_checksums: bool,
+	content_checksum: bool,
+	content_size: Option<u64>,
+	len: usize,
+}
+
+impl Lz4FrameHeader {
+	fn parse(image: &[u8]) -> Result<Self, Error> {
+		let (header, rest) = image.split_first_chunk::<6>().ok_or(Error::Truncated)?;
+		let flg = header[4];
+
+		if flg >> 6 != 0b01 {
+			return Err(Error::Unsupported);
+		}
+
+		let has_content_size = flg & (1 << 3) != 0;
+		let has_dict_id = flg & (1 << 0) != 0;
+		if has_dict_id {
+			return Err(Error::Unsupported);
+		}
+
+		let content_size = if has_content_size {
+			let content_size = rest.first_chunk::<8>().ok_or(Error::Truncated)?;
+			Some(u64::from_le_bytes(*content_size))
+		} else {
+			None
+		};
+
+		// Magic, FLG, BD, optional content size, and header checksum
+		let len = 6 + if has_content_size { 8 } else { 0 } + 1;
+
+		let checksum = *image.get(len - 1).ok_or(Error::Truncated)?;
+		if (xxh32(&image[4..len - 1]) >> 8) as u8 != checksum {
+			return Err(Error::ChecksumMismatch);
+		}
+
+		Ok(Self {
+			independent_blocks: flg & (1 << 5) != 0,
+			block_checksums: flg & (1 << 4) != 0,
+			content_checksum: flg & (1 << 2) != 0,
+			content_size,
+			len,
+		})
+	}
+}
+
+fn decompress_lz4_frame(image: &[u8], out: &mut [u8]) -> Result<usize, Error> {
+	const UNCOMPRESSED: u32 = 1 << 31;
+
+	let header = Lz4FrameHeader::parse(image)?;
+	let mut data = image.get(header.len..).ok_or(Error::Truncated)?;
+	let mut pos = 0;
+
+	loop {
+		let (block_size, rest) = data.split_first_chunk::<4>().ok_or(Error::Truncated)?;
+		let block_size = u32::from_le_bytes(*block_size);
+		data = rest;
+
+		if block_size == 0 {
+			break;
+		}
+
+		let len = usize::try_from(block_size & !UNCOMPRESSED).unwrap();
+		let (block, rest) = data.split_at_checked(len).ok_or(Error::Truncated)?;
+		data = rest;
+
+		if header.block_checksums {
+			let (checksum, rest) = data.split_first_chunk::<4>().ok_or(Error::Truncated)?;
+			if xxh32(block) != u32::from_le_bytes(*checksum) {
+				return Err(Error::ChecksumMismatch);
+			}
+			data = rest;
+		}
+
+		let (done, todo) = out.split_at_mut(pos);
+		let written = if block_size & UNCOMPRESSED != 0 {
+			let actual = todo.len();
+			todo.get_mut(..len)
+				.ok_or(Error::Lz4(Lz4Error::OutputTooSmall {
+					expected: len,
+					actual,
+				}))?
+				.copy_from_slice(block);
+			len
+		} else if header.independent_blocks {
+			lz4_flex::block::decompress_into(block, todo).map_err(Error::Lz4)?
+		} else {
+			let dict = &done[done.len().saturating_sub(LZ4_DICT_SIZE)..];
+			lz4_flex::block::decompress_into_with_dict(block, todo, dict).map_err(Error::Lz4)?
+		};
+		pos += written;
+	}
+
+	if header.content_checksum {
+		let checksum = data.first_chunk::<4>().ok_or(Error::Truncated)?;
+		if xxh32(&out[..pos]) != u32::from_le_bytes(*checksum) {
+			return Err(Error::ChecksumMismatch);
+		}
+	}
+
+	Ok(pos)
+}
+
+/// Computes the [XXH32] hash of `data` with a seed of 0, as used by LZ4 frames.
+///
+/// [XXH32]: https://github.com/Cyan4973/xxHash/blob/dev/doc/xxhash_spec.md
+fn xxh32(data: &[u8]) -> u32 {
+	const PRIME1: u32 = 0x9e37_79b1;
+	const PRIME2: u32 = 0x85eb_ca77;
+	const PRIME3: u32 = 0xc2b2_ae3d;
+	const PRIME4: u32 = 0x27d4_eb2f;
+	const PRIME5: u32 = 0x1656_67b1;
+
+	fn round(acc: u32, lane: u32) -> u32 {
+		acc.wrapping_add(lane.wrapping_mul(PRIME2))
+			.rotate_left(13)
+			.wrapping_mul(PRIME1)
+	}
+
+	let (stripes, rest) = data.as_chunks::<16>();
+	let mut acc = if stripes.is_empty() {
+		PRIME5
+	} else {
+		let mut v = [
+			PRIME1.wrapping_add(PRIME2),
+			PRIME2,
+			0,
+			PRIME1.wrapping_neg(),
+		];
+		for stripe in stripes {
+			let (lanes, _) = stripe.as_chunks::<4>();
+			for (v, lane) in v.iter_mut().zip(lanes) {
+				*v = round(*v, u32::from_le_bytes(*lane));
+			}
+		}
+		v[0].rotate_left(1)
+			.wrapping_add(v[1].rotate_left(7))
+			.wrapping_add(v[2].rotate_left(12))
+			.wrapping_add(v[3].rotate_left(18))
+	};
+	acc = acc.wrapping_add(data.len() as u32);
+
+	let (lanes, rest) = rest.as_chunks::<4>();
+	for lane in lanes {
+		acc = acc
+			.wrapping_add(u32::from_le_bytes(*lane).wrapping_mul(PRIME3))
+			.rotate_left(17)
+			.wrapping_mul(PRIME4);
+	}
+	for byte in rest {
+		acc = acc
+			.wrapping_add(u32::from(*byte).wrapping_mul(PRIME5))
+			.rotate_left(11)
+			.wrapping_mul(PRIME1);
+	}
+
+	acc ^= acc >> 15;
+	acc = acc.wrapping_mul(PRIME2);
+	acc ^= acc >> 13;
+	acc = acc.wrapping_mul(PRIME3);
+	acc ^= acc >> 16;
+	acc
+}
+
+#[cfg(all(test, not(target_os = "none")))]
+mod tests {
+	use alloc::vec;
+	use alloc::vec::Vec;
+
+	use super::*;
+
+	const CONTENT: &[u8] = b"Hermit Hermit Hermit Hermit\n";
+
+	/// `gzip -n -9`
+	const GZIP: &[u8] = &[
+		0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0xf3, 0x48, 0x2d, 0xca, 0xcd,
+		0x2c, 0x51, 0xf0, 0xc0, 0x42, 0x71, 0x01, 0x00, 0xfe, 0x22, 0x31, 0xe7, 0x1c, 0x00, 0x00,
+		0x00,
+	];
+
+	/// `zstd`
+	const ZSTD: &[u8] = &[
+		0x28, 0xb5, 0x2f, 0xfd, 0x24, 0x1c, 0x75, 0x00, 0x00, 0x40, 0x48, 0x65, 0x72, 0x6d, 0x69,
+		0x74, 0x20, 0x0a, 0x01, 0x00, 0xa2, 0x8b, 0x11, 0x24, 0x80, 0x66, 0x48,
+	];
+
+	/// `lz4 --content-size -BX`
+	const LZ4: &[u8] = &[
+		0x04, 0x22, 0x4d, 0x18, 0x7c, 0x40, 0x1c, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xaf,
+		0x10, 0x00, 0x00, 0x00, 0x7c, 0x48, 0x65, 0x72, 0x6d, 0x69, 0x74, 0x20, 0x07, 0x00, 0x50,
+		0x72, 0x6d, 0x69, 0x74, 0x0a, 0x0b, 0x41, 0x59, 0x5f, 0x00, 0x00, 0x00, 0x00, 0x54, 0x37,
+		0xef, 0xe6,
+	];
+
+	fn decompress(compression: Compression, image: &[u8]) -> Result<Vec<u8>, Error> {
+		let mut out = vec![0; compression.decompressed_size(image)?];
+		compression.decompress_into(image, &mut out)?;
+		Ok(out)
+	}
+
+	/// Builds an LZ4 frame with a content size from `(data, uncompressed)` blocks.
+	fn lz4_frame(flg: u8, content_size: u64, blocks: &[(&[u8], bool)]) -> Vec<u8> {
+		let mut frame = 0x184d2204u32.to_le_bytes().to_vec();
+		frame.extend([flg | (1 << 3), 0x40]);
+		frame.extend(content_size.to_le_bytes());
+		frame.push((xxh32(&frame[4..]) >> 8) as u8);
+		for (block, uncompressed) in blocks {
+			let size = block.len() as u32 | if *uncompressed { 1 << 31 } else { 0 };
+			frame.extend(size.to_le_bytes());
+			frame.extend(*block);
+		}
+		frame.extend(0u32.to_le_bytes());
+		frame
+	}
+
+	#[test]
+	fn detect() {
+		assert_eq!(Compression::detect(GZIP), Some(Compression::Gzip));
+		assert_eq!(Compression::detect(ZSTD), Some(Compression::Zstd));
+		assert_eq!(Compression::detect(LZ4), Some(Compression::Lz4));
+		assert_eq!(Compression::detect(b"\x7fELF\x02\x01\x01"), None);
+		assert_eq!(Compression::detect(&GZIP[..2]), None);
+	}
+
+	#[test]
+	fn decompressed_size() {
+		for (compression, image) in [
+			(Compression::Gzip, GZIP),
+			(Compression::Zstd, ZSTD),
+			(Compression::Lz4, LZ4),
+		] {
+			assert_eq!(compression.decompressed_size(image).unwrap(), CONTENT.len());
+		}
+
+		assert!(matches!(
+			Compression::Gzip.decompressed_size(&GZIP[..3]),
+			Err(Error::Truncated)
+		));
+
+		let mut lz4 = LZ4[..6].to_vec();
+		lz4[4] &= !(1 << 3);
+		lz4.push((xxh32(&lz4[4..]) >> 8) as u8);
+		assert!(matches!(
+			Compression::Lz4.decompressed_size(&lz4),
+			Err(Error::UnknownSize)
+		));
+	}
+
+	#[test]
+	fn gzip() {
+		assert_eq!(decompress(Compression::Gzip, GZIP).unwrap(), CONTENT);
+	}
+
+	#[test]
+	fn gzip_header_fields() {
+		let mut gzip = GZIP[..10].to_vec();
+		// FHCRC, FEXTRA, FNAME, and FCOMMENT
+		gzip[3] = 0b1_1110;
+		gzip.extend([3, 0, b'H', b'M', b'T']);
+		gzip.extend(b"hermit-app\0");
+		gzip.extend(b"comment\0");
+		gzip.extend([0xaa, 0xbb]);
+		gzip.extend(&GZIP[10..]);
+
+		assert_eq!(decompress(Compression::Gzip, &gzip).unwrap(), CONTENT);
+
+		// Within the file name
+		assert!(matches!(
+			Compression::Gzip.decompress_into(&gzip[..20], &mut [0; 28]),
+			Err(Error::Truncated)
+		));
+	}
+
+	#[test]
+	fn gzip_checksum() {
+		let mut gzip = GZIP.to_vec();
+		let crc32 = gzip.len() - 8;
+		gzip[crc32] ^= 1;
+		assert!(matches!(
+			decompress(Compression::Gzip, &gzip),
+			Err(Error::ChecksumMismatch)
+		));
+	}
+
+	#[test]
+	fn zstd() {
+		assert_eq!(decompress(Compression::Zstd, ZSTD).unwrap(), CONTENT);
+	}
+
+	#[test]
+	fn lz4_independent_blocks() {
+		assert_eq!(decompress(Compression::Lz4, LZ4).unwrap(), CONTENT);
+
+		// Without block or content checksums
+		let block = &LZ4[19..35];
+		let lz4 = lz4_frame(0x60, CONTENT.len() as u64, &[(block, false)]);
+		assert_eq!(decompress(Compression::Lz4, &lz4).unwrap(), CONTENT);
+	}
+
+	#[test]
+	fn lz4_linked_blocks() {
+		// An uncompressed block followed by a match into it
+		let blocks: &[(&[u8], bool)] = &[
+			(b"Hermit!!", true),
+			(&[0x04, 0x08, 0x00, 0x10, b'\n'], false),
+		];
+		let expected = b"Hermit!!Hermit!!\n";
+
+		let lz4 = lz4_frame(0x40, expected.len() as u64, blocks);
+		assert_eq!(decompress(Compression::Lz4, &lz4).unwrap(), expected);
+
+		let lz4 = lz4_frame(0x60, expected.len() as u64, blocks);
+		assert!(matches!(
+			decompress(Compression::Lz4, &lz4),
+			Err(Error::Lz4(_))
+		));
+	}
+
+	#[test]
+	fn lz4_truncated() {
+		for len in 0..LZ4.len() {
+			let mut out = [0; CONTENT.len()];
+			assert!(
+				matches!(
+					Compression::Lz4.decompress_into(&LZ4[..len], &mut out),
+					Err(Error::Truncated)
+				),
+				"len = {len}"
+			);
+		}
+	}
+
+	#[test]
+	fn lz4_checksums() {
+		// Header, block, and content checksum
+		for offset in [14, 35, 43] {
+			let mut lz4 = LZ4.to_vec();
+			lz4[offset] ^= 1;
+			assert!(matches!(
+				decompress(Compression::Lz4, &lz4),
+				Err(Error::ChecksumMismatch)
+			));
+		}
+	}
+
+	#[test]
+	fn xxh32_reference() {
+		assert_eq!(super::xxh32(b""), 0x02cc5d05);
+		assert_eq!(super::xxh32(b"a"), 0x550d7456);
+		assert_eq!(super::xxh32(b"abc"), 0x32d153ff);
+		assert_eq!(
+			super::xxh32(b"Nobody inspects the spammish repetition"),
+			0xe2293b2f
+		);
+	}
+}
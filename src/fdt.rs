@@ -1,14 +1,39 @@
 use alloc::format;
 use alloc::string::String;
 use alloc::vec::Vec;
+use core::iter;
 use core::ops::Range;
 
+use log::info;
 use vm_fdt::{FdtWriter, FdtWriterNode, FdtWriterResult};
 
+use crate::fdt_header::{
+	OFF_DT_STRINGS, OFF_DT_STRUCT, OFF_MEM_RSVMAP, TOTALSIZE, read_be32, write_be32,
+};
+use crate::framebuffer::Framebuffer;
+use crate::reserved_memory::{Reservation, ReservedMemory};
+
 pub struct Fdt {
 	writer: FdtWriter,
 	root_node: FdtWriterNode,
 	bootargs: Option<String>,
+	rng_seed: Option<Vec<u8>>,
+	app_sha256: Option<[u8; 32]>,
+	framebuffer: Option<Framebuffer>,
+	modules: Vec<Module>,
+	reserved_memory: Vec<ReservedMemory>,
+	/// The `/reserved-memory` children of merged device trees.
+	#[cfg(any(
+		feature = "linux",
+		all(target_os = "uefi", not(target_arch = "x86_64"))
+	))]
+	merged_reserved_memory: Vec<merge::Node>,
+}
+
+/// An additional boot module that is forwarded to the kernel.
+struct Module {
+	memory: Range<u64>,
+	string: String,
 }
 
 impl Fdt {
@@ -21,24 +46,131 @@ impl Fdt {
 		writer.property_u32("#size-cells", 0x2)?;
 
 		let bootargs = None;
+		let rng_seed = None;
+		let app_sha256 = None;
+		let framebuffer = None;
+		let modules = Vec::new();
+		let reserved_memory = Vec::new();
 
 		Ok(Self {
 			writer,
 			root_node,
 			bootargs,
+			rng_seed,
+			app_sha256,
+			framebuffer,
+			modules,
+			reserved_memory,
+			#[cfg(any(
+				feature = "linux",
+				all(target_os = "uefi", not(target_arch = "x86_64"))
+			))]
+			merged_reserved_memory: Vec::new(),
 		})
 	}
 
-	pub fn finish(mut self) -> FdtWriterResult<Vec<u8>> {
+	pub fn finish(self) -> FdtWriterResult<Vec<u8>> {
+		let (fdt, reserved_memory) = self.finish_structure()?;
+		Ok(with_memory_reservations(&fdt, &reserved_memory))
+	}
+
+	/// Finishes the device tree without adding entries to the memory reservation block.
+	///
+	/// Unlike [`Self::finish`], the result does not depend on where the device tree ends up in memory.
+	#[cfg(target_os = "uefi")]
+	pub fn finish_unreserved(self) -> FdtWriterResult<Vec<u8>> {
+		self.finish_structure().map(|(fdt, _reserved_memory)| fdt)
+	}
+
+	/// Finishes the device tree and returns it with the reserved memory regions.
+	fn finish_structure(mut self) -> FdtWriterResult<(Vec<u8>, Vec<ReservedMemory>)> {
 		let chosen_node = self.writer.begin_node("chosen")?;
 		if let Some(bootargs) = &self.bootargs {
 			self.writer.property_string("bootargs", bootargs)?;
 		}
+		if let Some(rng_seed) = &self.rng_seed {
+			self.writer.property("rng-seed", rng_seed)?;
+		}
+		if let Some(app_sha256) = &self.app_sha256 {
+			self.writer
+				.property(crate::hash::FDT_PROPERTY, app_sha256)?;
+		}
+		if !self.modules.is_empty() || self.framebuffer.is_some() {
+			self.writer.property_u32("#address-cells", 0x2)?;
+			self.writer.property_u32("#size-cells", 0x2)?;
+		}
+		if let Some(framebuffer) = &self.framebuffer {
+			let address = framebuffer.address;
+			let framebuffer_node = self
+				.writer
+				.begin_node(&format!("framebuffer@{address:x}"))?;
+			self.writer
+				.property_string("compatible", "simple-framebuffer")?;
+			self.writer
+				.property_array_u64("reg", &[address, framebuffer.size()])?;
+			self.writer.property_u32("width", framebuffer.width)?;
+			self.writer.property_u32("height", framebuffer.height)?;
+			self.writer.property_u32("stride", framebuffer.stride)?;
+			self.writer
+				.property_string("format", framebuffer.format.name())?;
+			self.writer.end_node(framebuffer_node)?;
+		}
+		for module in &self.modules {
+			let Range { start, end } = module.memory;
+			let module_node = self.writer.begin_node(&format!("module@{start:x}"))?;
+			self.writer
+				.property_string("compatible", "multiboot,module")?;
+			self.writer
+				.property_array_u64("reg", &[start, end - start])?;
+			self.writer.property_string("bootargs", &module.string)?;
+			self.writer.end_node(module_node)?;
+		}
 		self.writer.end_node(chosen_node)?;
 
+		#[cfg(any(
+			feature = "linux",
+			all(target_os = "uefi", not(target_arch = "x86_64"))
+		))]
+		let has_merged_reserved_memory = !self.merged_reserved_memory.is_empty();
+		#[cfg(not(any(
+			feature = "linux",
+			all(target_os = "uefi", not(target_arch = "x86_64"))
+		)))]
+		let has_merged_reserved_memory = false;
+
+		if !self.reserved_memory.is_empty() || has_merged_reserved_memory {
+			let reserved_memory_node = self.writer.begin_node("reserved-memory")?;
+			self.writer.property_u32("#address-cells", 0x2)?;
+			self.writer.property_u32("#size-cells", 0x2)?;
+			self.writer.property_null("ranges")?;
+			for reserved_memory in &self.reserved_memory {
+				let Range { start, end } = reserved_memory.memory;
+				let name = reserved_memory.name;
+				let node = self.writer.begin_node(&format!("{name}@{start:x}"))?;
+				self.writer
+					.property_string("compatible", &format!("hermit,{name}"))?;
+				self.writer
+					.property_array_u64("reg", &[start, end - start])?;
+				match reserved_memory.reservation {
+					Reservation::Reclaimable => self.writer.property_null("reusable")?,
+					Reservation::Permanent => self.writer.property_null("no-map")?,
+				}
+				self.writer.end_node(node)?;
+			}
+			#[cfg(any(
+				feature = "linux",
+				all(target_os = "uefi", not(target_arch = "x86_64"))
+			))]
+			for node in &self.merged_reserved_memory {
+				node.write(&mut self.writer)?;
+			}
+			self.writer.end_node(reserved_memory_node)?;
+		}
+
 		self.writer.end_node(self.root_node)?;
 
-		self.writer.finish()
+		let fdt = self.writer.finish()?;
+		Ok((fdt, self.reserved_memory))
 	}
 
 	pub fn bootargs(mut self, bootargs: String) -> FdtWriterResult<Self> {
@@ -48,7 +180,68 @@ impl Fdt {
 		Ok(self)
 	}
 
-	#[cfg_attr(all(target_arch = "x86_64", not(target_os = "uefi")), expect(unused))]
+	/// Sets `/chosen/rng-seed` for seeding the kernel's random number generator.
+	pub fn rng_seed(mut self, rng_seed: &[u8]) -> FdtWriterResult<Self> {
+		assert!(self.rng_seed.is_none());
+		self.rng_seed = Some(rng_seed.into());
+
+		Ok(self)
+	}
+
+	/// Sets `/chosen/hermit,app-sha256` to the SHA-256 digest of the Hermit application.
+	pub fn app_sha256(mut self, app_sha256: [u8; 32]) -> FdtWriterResult<Self> {
+		assert!(self.app_sha256.is_none());
+		self.app_sha256 = Some(app_sha256);
+
+		Ok(self)
+	}
+
+	/// Adds the firmware's framebuffer to `/chosen` as a `simple-framebuffer` node.
+	#[cfg_attr(feature = "pvh", expect(unused))]
+	pub fn framebuffer(mut self, framebuffer: Framebuffer) -> FdtWriterResult<Self> {
+		assert!(self.framebuffer.is_none());
+		info!("Passing framebuffer to the kernel: {framebuffer}");
+		self.framebuffer = Some(framebuffer);
+
+		Ok(self)
+	}
+
+	/// Adds an additional boot module to `/chosen`.
+	///
+	/// Modules follow the `multiboot,module` binding, with the module string in `bootargs`.
+	/// Like the initrd, modules are also added to `/reserved-memory` as reclaimable regions.
+	#[cfg_attr(feature = "linux", expect(unused))]
+	pub fn module(mut self, memory: Range<u64>, string: &str) -> FdtWriterResult<Self> {
+		self = self.reserved_memory(ReservedMemory::new(
+			memory.clone(),
+			"module",
+			Reservation::Reclaimable,
+		))?;
+		self.modules.push(Module {
+			memory,
+			string: string.into(),
+		});
+
+		Ok(self)
+	}
+
+	/// Adds a reserved memory region to `/reserved-memory`.
+	///
+	/// Permanent regions are also added to the memory reservation block.
+	pub fn reserved_memory(mut self, reserved_memory: ReservedMemory) -> FdtWriterResult<Self> {
+		info!(
+			"Reserving {} at [{:#x} - {:#x}] ({:?})",
+			reserved_memory.name,
+			reserved_memory.memory.start,
+			reserved_memory.memory.end,
+			reserved_memory.reservation
+		);
+		self.reserved_memory.push(reserved_memory);
+
+		Ok(self)
+	}
+
+	#[cfg_attr(any(feature = "linux", feature = "multiboot"), expect(unused))]
 	pub fn rsdp(mut self, rsdp: u64) -> FdtWriterResult<Self> {
 		let rsdp_node = self.writer.begin_node(&format!("hermit,rsdp@{rsdp:x}"))?;
 		self.writer.property_array_u64("reg", &[rsdp, 1])?;
@@ -86,6 +279,47 @@ impl Fdt {
 	}
 }
 
+/// Adds the permanent regions of `reserved_memory` to the memory reservation block of `fdt`.
+///
+/// The returned device tree also reserves itself, since the kernel keeps using it.
+fn with_memory_reservations(fdt: &[u8], reserved_memory: &[ReservedMemory]) -> Vec<u8> {
+	let header = |offset: usize| read_be32(fdt, offset).unwrap();
+
+	let permanent = reserved_memory
+		.iter()
+		.filter(|reserved_memory| reserved_memory.reservation == Reservation::Permanent)
+		.map(|reserved_memory| reserved_memory.memory.clone());
+
+	// We know the final location of the device tree by allocating its exact size upfront.
+	let entries_len = 16 * (permanent.clone().count() + 1);
+	let len = fdt.len() + entries_len;
+	let mut new_fdt = Vec::<u8>::with_capacity(len);
+	let addr = new_fdt.as_ptr().expose_provenance() as u64;
+
+	// We insert our entries at the front of the memory reservation block.
+	let off_mem_rsvmap = header(OFF_MEM_RSVMAP);
+	new_fdt.extend_from_slice(&fdt[..off_mem_rsvmap]);
+	for memory in permanent.chain(iter::once(addr..addr + len as u64)) {
+		new_fdt.extend_from_slice(&memory.start.to_be_bytes());
+		new_fdt.extend_from_slice(&(memory.end - memory.start).to_be_bytes());
+	}
+	new_fdt.extend_from_slice(&fdt[off_mem_rsvmap..]);
+
+	write_be32(&mut new_fdt, TOTALSIZE, len);
+	write_be32(
+		&mut new_fdt,
+		OFF_DT_STRUCT,
+		header(OFF_DT_STRUCT) + entries_len,
+	);
+	write_be32(
+		&mut new_fdt,
+		OFF_DT_STRINGS,
+		header(OFF_DT_STRINGS) + entries_len,
+	);
+
+	new_fdt
+}
+
 #[cfg(feature = "multiboot")]
 mod x86_64 {
 	use multiboot::information::{MemoryMapIter, MemoryType};
@@ -111,6 +345,121 @@ mod x86_64 {
 	}
 }
 
+#[cfg(any(
+	feature = "linux",
+	all(target_os = "uefi", not(target_arch = "x86_64"))
+))]
+mod merge {
+	use alloc::string::String;
+	use alloc::vec::Vec;
+
+	use fdt::node::{CellSizes, FdtNode};
+	use log::{info, warn};
+	use vm_fdt::{FdtWriter, FdtWriterResult};
+
+	impl super::Fdt {
+		/// Merges the nodes of `dtb` into the device tree.
+		///
+		/// `/chosen` and `/memory` nodes are skipped, since the loader provides them itself.
+		/// The root properties are copied, except for `compatible` and the cell sizes.
+		/// The children of `/reserved-memory` are added to the loader's `/reserved-memory`.
+		///
+		/// This has to be called before any node is added to the device tree.
+		pub fn merge(mut self, dtb: &[u8]) -> FdtWriterResult<Self> {
+			let dtb = match fdt::Fdt::new(dtb) {
+				Ok(dtb) => dtb,
+				Err(err) => {
+					warn!("Ignoring invalid device tree: {err:?}");
+					return Ok(self);
+				}
+			};
+
+			let root = dtb.find_node("/").unwrap();
+			if !has_loader_cell_sizes(root) {
+				warn!("Ignoring device tree with unsupported root cell sizes");
+				return Ok(self);
+			}
+
+			for property in root.properties() {
+				if matches!(
+					property.name,
+					"compatible" | "#address-cells" | "#size-cells"
+				) {
+					continue;
+				}
+
+				self.writer.property(property.name, property.value)?;
+			}
+
+			for node in root.children() {
+				let name = node.name.split('@').next().unwrap();
+				match name {
+					"chosen" | "memory" => {
+						info!("Skipping device tree node /{}", node.name);
+					}
+					"reserved-memory" if has_loader_cell_sizes(node) => {
+						info!("Merging device tree node /{}", node.name);
+						self.merged_reserved_memory
+							.extend(node.children().map(Node::new));
+					}
+					"reserved-memory" => {
+						warn!(
+							"Skipping device tree node /{} with unsupported cell sizes",
+							node.name
+						);
+					}
+					_ => {
+						info!("Merging device tree node /{}", node.name);
+						Node::new(node).write(&mut self.writer)?;
+					}
+				}
+			}
+
+			Ok(self)
+		}
+	}
+
+	/// Returns whether the children of `node` use the cell sizes of the loader's device tree.
+	fn has_loader_cell_sizes(node: FdtNode<'_, '_>) -> bool {
+		let CellSizes {
+			address_cells,
+			size_cells,
+		} = node.cell_sizes();
+		address_cells == 2 && size_cells == 2
+	}
+
+	/// An owned copy of a device tree node.
+	pub struct Node {
+		name: String,
+		properties: Vec<(String, Vec<u8>)>,
+		children: Vec<Node>,
+	}
+
+	impl Node {
+		fn new(node: FdtNode<'_, '_>) -> Self {
+			Self {
+				name: node.name.into(),
+				properties: node
+					.properties()
+					.map(|property| (property.name.into(), property.value.into()))
+					.collect(),
+				children: node.children().map(Self::new).collect(),
+			}
+		}
+
+		pub fn write(&self, writer: &mut FdtWriter) -> FdtWriterResult<()> {
+			let writer_node = writer.begin_node(&self.name)?;
+			for (name, value) in &self.properties {
+				writer.property(name, value)?;
+			}
+			for child in &self.children {
+				child.write(writer)?;
+			}
+			writer.end_node(writer_node)
+		}
+	}
+}
+
 #[cfg(target_os = "uefi")]
 mod uefi {
 	use core::fmt;
@@ -1,9 +1,540 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::Range;
+use core::{fmt, iter};
+
+use align_address::Align;
 use goblin::elf64::header::{EI_DATA, ELFDATA2LSB, ELFMAG, Header, SELFMAG};
+use log::{info, warn};
+
+use crate::fdt_header::{
+	OFF_DT_STRINGS, OFF_DT_STRUCT, OFF_MEM_RSVMAP, SIZE_DT_STRINGS, SIZE_DT_STRUCT, TOTALSIZE,
+	read_be32, write_be32,
+};
+use crate::reserved_memory::{Reservation, ReservedMemory};
+use crate::{hash, random};
+
+const FDT_BEGIN_NODE: u32 = 0x1;
+const FDT_END_NODE: u32 = 0x2;
+const FDT_PROP: u32 = 0x3;
+const FDT_NOP: u32 = 0x4;
+const FDT_END: u32 = 0x9;
 
 pub trait FdtExt {
 	fn find_module_start(&self) -> Option<&'static [u8]>;
 	fn find_linux_initrd(&self) -> Option<&'static [u8]>;
 	fn find_kernel(&self) -> Option<&'static [u8]>;
+
+	/// Logs all `/chosen/module@` nodes except for the kernel.
+	///
+	/// This device tree is forwarded to the kernel, which thus receives these modules as is.
+	fn log_additional_modules(&self);
+
+	/// Returns the memory of all `/chosen/module@` nodes except for the kernel.
+	fn module_regions(&self) -> impl Iterator<Item = Range<u64>>;
+
+	/// Returns the RAM regions of all memory nodes.
+	fn memory_regions(&self) -> impl Iterator<Item = Range<u64>>;
+
+	/// Returns the start of the first `len` bytes of RAM at or after `start` that do not overlap `reserved`
+	/// and the end of the free memory after them.
+	///
+	/// The start is aligned to `align`.
+	/// Allocations do not span multiple memory regions.
+	fn find_free_memory(
+		&self,
+		start: u64,
+		len: u64,
+		align: u64,
+		reserved: &[Range<u64>],
+	) -> Option<(u64, u64)> {
+		let mut start = start.align_up(align);
+		loop {
+			// The region containing `start` or the lowest region after it
+			let region = self
+				.memory_regions()
+				.filter(|region| region.end > start)
+				.min_by_key(|region| region.start)?;
+			if region.start > start {
+				start = region.start.align_up(align);
+				continue;
+			}
+
+			let end = start + len;
+			if end > region.end {
+				start = region.end;
+				continue;
+			}
+
+			if let Some(reserved) = reserved
+				.iter()
+				.find(|reserved| reserved.start < end && start < reserved.end)
+			{
+				start = reserved.end.align_up(align);
+				continue;
+			}
+
+			let free_end = reserved
+				.iter()
+				.map(|reserved| reserved.start)
+				.filter(|reserved_start| *reserved_start >= end)
+				.fold(region.end, u64::min);
+			return Some((start, free_end));
+		}
+	}
+
+	/// Returns the range from the start of the lowest to the end of the highest memory region.
+	fn phys_addr_range(&self) -> Range<u64> {
+		let start = self
+			.memory_regions()
+			.map(|region| region.start)
+			.min()
+			.unwrap();
+		let end = self
+			.memory_regions()
+			.map(|region| region.end)
+			.max()
+			.unwrap();
+		start..end
+	}
+}
+
+/// Re-randomizes `/chosen/rng-seed` of the device tree at `fdt_ptr` in place.
+///
+/// This consumes the firmware's seed, so that the kernel never receives it as is.
+/// Returns `false` if the device tree has no `/chosen/rng-seed`.
+///
+/// # Safety
+///
+/// `fdt_ptr` must point to a valid, writable device tree that is not borrowed.
+unsafe fn rerandomize_rng_seed(fdt_ptr: *mut u8) -> bool {
+	let range = {
+		let fdt = unsafe { fdt::Fdt::from_ptr(fdt_ptr).unwrap() };
+		let Some(rng_seed) = fdt
+			.find_node("/chosen")
+			.and_then(|chosen| chosen.property("rng-seed"))
+		else {
+			info!("The device tree has no /chosen/rng-seed");
+			return false;
+		};
+		let start = rng_seed.value.as_ptr().addr() - fdt_ptr.addr();
+		start..start + rng_seed.value.len()
+	};
+
+	let len = range.len();
+	let rng_seed = unsafe { core::slice::from_raw_parts_mut(fdt_ptr.add(range.start), len) };
+
+	let mut source = None;
+	for chunk in rng_seed.chunks_mut(8) {
+		let mut entropy = [0; 8];
+		source = Some(random::fill_bytes(&mut entropy));
+		for (byte, entropy) in chunk.iter_mut().zip(entropy) {
+			*byte ^= entropy;
+		}
+	}
+
+	if let Some(source) = source {
+		info!("Re-randomized /chosen/rng-seed ({len} B) with entropy from {source}");
+	}
+
+	true
+}
+
+/// Prepares the firmware's device tree at `fdt_ptr` for the kernel.
+///
+/// This re-randomizes `/chosen/rng-seed` or adds one, adds the application digest and `bootargs` to `/chosen`,
+/// and reserves the loader, the initrd, and additional modules.
+/// Returns the patched copy or, if the device tree cannot be patched, `fdt_ptr`.
+///
+/// # Safety
+///
+/// `fdt_ptr` must point to a valid, writable device tree that is not borrowed.
+pub unsafe fn prepare(fdt_ptr: *mut u8, bootargs: Option<&str>, app_sha256: [u8; 32]) -> *const u8 {
+	let has_rng_seed = unsafe { rerandomize_rng_seed(fdt_ptr) };
+	let rng_seed = if has_rng_seed { None } else { random::seed() };
+
+	let fdt = unsafe { fdt::Fdt::from_ptr(fdt_ptr).unwrap() };
+
+	let mut properties: Vec<(&str, &[u8])> = vec![(hash::FDT_PROPERTY, &app_sha256)];
+	if let Some(rng_seed) = &rng_seed {
+		properties.push(("rng-seed", rng_seed));
+	}
+	let command_line = fdt
+		.find_node("/chosen")
+		.and_then(|chosen| chosen.property("bootargs"))
+		.and_then(|bootargs| bootargs.as_str());
+	let bootargs = bootargs
+		.filter(|bootargs| Some(*bootargs) != command_line)
+		.map(|bootargs| [bootargs.as_bytes(), &[0]].concat());
+	if let Some(bootargs) = &bootargs {
+		properties.push(("bootargs", bootargs));
+	}
+
+	let reserved_memory = [
+		ReservedMemory::loader(),
+		ReservedMemory::reclaimable(fdt.find_kernel().unwrap(), "initrd"),
+	]
+	.into_iter()
+	.chain(
+		fdt.module_regions()
+			.map(|memory| ReservedMemory::new(memory, "module", Reservation::Reclaimable)),
+	)
+	.collect::<Vec<_>>();
+
+	unsafe { patch(fdt_ptr, &properties, &reserved_memory) }
+		.map_or(fdt_ptr.cast_const(), <[u8]>::as_ptr)
+}
+
+/// Copies the device tree at `fdt_ptr` to new memory, adds `properties` to `/chosen`, and adds `reserved_memory`.
+///
+/// Existing properties of `/chosen` with the same names are replaced by `FDT_NOP` tokens.
+///
+/// Reserved memory is added to `/reserved-memory`, which is created if necessary.
+/// Permanent regions and the new device tree itself are also added to the memory reservation block.
+///
+/// Returns `None` and leaves the device tree unchanged if it has no `/chosen` node or a layout that we cannot patch.
+///
+/// # Safety
+///
+/// `fdt_ptr` must point to a valid device tree.
+unsafe fn patch(
+	fdt_ptr: *const u8,
+	properties: &[(&str, &[u8])],
+	reserved_memory: &[ReservedMemory],
+) -> Option<&'static [u8]> {
+	/// The names of the properties that we write into `/reserved-memory`.
+	const RESERVED_MEMORY_NAMES: [&str; 7] = [
+		"#address-cells",
+		"#size-cells",
+		"ranges",
+		"compatible",
+		"reg",
+		"reusable",
+		"no-map",
+	];
+
+	let total_size = unsafe { fdt::Fdt::from_ptr(fdt_ptr).ok()?.total_size() };
+	let blob = unsafe { core::slice::from_raw_parts(fdt_ptr, total_size) };
+
+	let off_mem_rsvmap = read_be32(blob, OFF_MEM_RSVMAP)?;
+	let off_dt_struct = read_be32(blob, OFF_DT_STRUCT)?;
+	let off_dt_strings = read_be32(blob, OFF_DT_STRINGS)?;
+	let size_dt_strings = read_be32(blob, SIZE_DT_STRINGS)?;
+	let size_dt_struct = read_be32(blob, SIZE_DT_STRUCT)?;
+
+	// We insert into each block, which thus have to be in the standard order.
+	if off_mem_rsvmap > off_dt_struct || off_dt_struct + size_dt_struct > off_dt_strings {
+		warn!("The device tree blocks are not in the standard order");
+		return None;
+	}
+
+	let property_name = |nameoff: usize| {
+		let names = blob.get(off_dt_strings + nameoff..)?;
+		let len = names.iter().position(|b| *b == 0)?;
+		Some(&names[..len])
+	};
+
+	// Find the properties of `/chosen`, right after its name, and the end of `/reserved-memory`.
+	let mut offset = off_dt_struct;
+	let mut depth = 0;
+	let mut in_chosen = false;
+	let mut in_reserved_memory = false;
+	let mut chosen_at = None;
+	let mut replaced = Vec::new();
+	let mut reserved_memory_at = None;
+	let mut cells = (2, 1);
+	let mut root_end_at = None;
+	loop {
+		let token_at = offset;
+		let token = u32::try_from(read_be32(blob, offset)?).unwrap();
+		offset += 4;
+		match token {
+			FDT_BEGIN_NODE => {
+				let name_len = blob.get(offset..)?.iter().position(|b| *b == 0)?;
+				let name = &blob[offset..offset + name_len];
+				offset = (offset + name_len + 1).next_multiple_of(4);
+				depth += 1;
+				if depth == 2 && name == b"chosen" {
+					chosen_at = Some(offset);
+				}
+				if depth == 2 {
+					in_chosen = name == b"chosen";
+					in_reserved_memory = name == b"reserved-memory";
+				}
+			}
+			FDT_END_NODE => {
+				if depth == 2 && in_reserved_memory {
+					reserved_memory_at = Some(token_at);
+					in_reserved_memory = false;
+				}
+				if depth == 1 {
+					root_end_at = Some(token_at);
+				}
+				depth -= 1;
+			}
+			FDT_PROP => {
+				let len = read_be32(blob, offset)?;
+				let nameoff = read_be32(blob, offset + 4)?;
+				let end = (offset + 8 + len).next_multiple_of(4);
+				let is_replaced = || {
+					properties
+						.iter()
+						.any(|(name, _value)| property_name(nameoff) == Some(name.as_bytes()))
+				};
+				if depth == 2 && in_chosen && is_replaced() {
+					replaced.push(token_at..end);
+				}
+				if depth == 2 && in_reserved_memory && len == 4 {
+					let value = read_be32(blob, offset + 8)?;
+					match property_name(nameoff)? {
+						b"#address-cells" => cells.0 = value,
+						b"#size-cells" => cells.1 = value,
+						_ => {}
+					}
+				}
+				offset = end;
+			}
+			FDT_NOP => {}
+			FDT_END => break,
+			_ => {
+				warn!("The device tree has an invalid structure block");
+				return None;
+			}
+		}
+	}
+
+	let Some(chosen_at) = chosen_at else {
+		warn!("The device tree has no /chosen node");
+		return None;
+	};
+
+	let has_reserved_memory = reserved_memory_at.is_some();
+	if !has_reserved_memory {
+		cells = (2, 2);
+	}
+	let reserved_memory_at = reserved_memory_at.or(root_end_at)?;
+	if !matches!(cells, (1 | 2, 1 | 2)) {
+		warn!("Unsupported cells in /reserved-memory: {cells:?}");
+		return None;
+	}
+
+	let names = || {
+		properties
+			.iter()
+			.map(|(name, _value)| *name)
+			.chain(RESERVED_MEMORY_NAMES)
+	};
+	let name_offset = |name: &str| {
+		let preceding = names()
+			.take_while(|other| *other != name)
+			.map(|other| other.len() + 1)
+			.sum::<usize>();
+		size_dt_strings + preceding
+	};
+
+	let permanent = || {
+		reserved_memory
+			.iter()
+			.filter(|reserved_memory| reserved_memory.reservation == Reservation::Permanent)
+			.map(|reserved_memory| reserved_memory.memory.clone())
+	};
+
+	let write_chosen_properties = |cursor: &mut Cursor<'_>| {
+		for (name, value) in properties {
+			cursor.prop(name_offset(name), value);
+		}
+	};
+
+	let write_reserved_memory = |cursor: &mut Cursor<'_>| {
+		if !has_reserved_memory {
+			cursor.be32(FDT_BEGIN_NODE);
+			cursor.name(format_args!("reserved-memory"));
+			cursor.prop(name_offset("#address-cells"), &2u32.to_be_bytes());
+			cursor.prop(name_offset("#size-cells"), &2u32.to_be_bytes());
+			cursor.prop(name_offset("ranges"), &[]);
+		}
+
+		for reserved_memory in reserved_memory {
+			let ReservedMemory {
+				memory,
+				name,
+				reservation,
+			} = reserved_memory;
+
+			cursor.be32(FDT_BEGIN_NODE);
+			cursor.name(format_args!("{name}@{:x}", memory.start));
+
+			let compatible_len = "hermit,".len() + name.len() + 1;
+			cursor.be32(FDT_PROP);
+			cursor.be32(compatible_len.try_into().unwrap());
+			cursor.be32(name_offset("compatible").try_into().unwrap());
+			cursor.name(format_args!("hermit,{name}"));
+
+			cursor.be32(FDT_PROP);
+			cursor.be32(((cells.0 + cells.1) * 4).try_into().unwrap());
+			cursor.be32(name_offset("reg").try_into().unwrap());
+			cursor.cells(cells.0, memory.start);
+			cursor.cells(cells.1, memory.end - memory.start);
+
+			let flag = match reservation {
+				Reservation::Reclaimable => "reusable",
+				Reservation::Permanent => "no-map",
+			};
+			cursor.prop(name_offset(flag), &[]);
+
+			cursor.be32(FDT_END_NODE);
+		}
+
+		if !has_reserved_memory {
+			cursor.be32(FDT_END_NODE);
+		}
+	};
+
+	type Insertion<'a> = (usize, &'a dyn Fn(&mut Cursor<'_>));
+	let mut insertions: [Insertion<'_>; 2] = [
+		(chosen_at, &write_chosen_properties),
+		(reserved_memory_at, &write_reserved_memory),
+	];
+	insertions.sort_by_key(|(offset, _write)| *offset);
+
+	// Copies `blob[range]`, replacing the properties in `replaced` with `FDT_NOP` tokens.
+	let copy = |cursor: &mut Cursor<'_>, range: Range<usize>| {
+		let mut offset = range.start;
+		for replaced in replaced
+			.iter()
+			.filter(|replaced| range.contains(&replaced.start))
+		{
+			cursor.bytes(&blob[offset..replaced.start]);
+			for _ in replaced.clone().step_by(4) {
+				cursor.be32(FDT_NOP);
+			}
+			offset = replaced.end;
+		}
+		cursor.bytes(&blob[offset..range.end]);
+	};
+
+	let strings_end = off_dt_strings + size_dt_strings;
+	let write = |cursor: &mut Cursor<'_>, fdt_range: Range<u64>| {
+		cursor.bytes(&blob[..off_mem_rsvmap]);
+		for memory in permanent().chain(iter::once(fdt_range)) {
+			cursor.be64(memory.start);
+			cursor.be64(memory.end - memory.start);
+		}
+		let rsvmap_end = cursor.pos;
+
+		let mut offset = off_mem_rsvmap;
+		for (insert_at, write_insertion) in insertions {
+			copy(cursor, offset..insert_at);
+			write_insertion(cursor);
+			offset = insert_at;
+		}
+		copy(cursor, offset..strings_end);
+
+		for name in names() {
+			cursor.name_unaligned(name);
+		}
+
+		rsvmap_end - off_mem_rsvmap
+	};
+
+	let mut cursor = Cursor { buf: None, pos: 0 };
+	let rsvmap_len = write(&mut cursor, 0..0);
+	let new_total_size = cursor.pos;
+	let names_len = names().map(|name| name.len() + 1).sum::<usize>();
+	let struct_len = new_total_size - strings_end - rsvmap_len - names_len;
+
+	let new_blob = unsafe {
+		let addr = crate::arch::get_memory(new_total_size as u64);
+		let ptr = core::ptr::with_exposed_provenance_mut::<u8>(addr.try_into().unwrap());
+		core::ptr::write_bytes(ptr, 0, new_total_size);
+		core::slice::from_raw_parts_mut(ptr, new_total_size)
+	};
+	let addr = new_blob.as_ptr().expose_provenance() as u64;
+
+	let mut cursor = Cursor {
+		buf: Some(new_blob),
+		pos: 0,
+	};
+	write(&mut cursor, addr..addr + new_total_size as u64);
+	let new_blob = cursor.buf.unwrap();
+
+	write_be32(new_blob, TOTALSIZE, new_total_size);
+	write_be32(new_blob, OFF_DT_STRUCT, off_dt_struct + rsvmap_len);
+	write_be32(
+		new_blob,
+		OFF_DT_STRINGS,
+		off_dt_strings + rsvmap_len + struct_len,
+	);
+	write_be32(new_blob, SIZE_DT_STRINGS, size_dt_strings + names_len);
+	write_be32(new_blob, SIZE_DT_STRUCT, size_dt_struct + struct_len);
+
+	Some(new_blob)
+}
+
+/// A cursor for writing a device tree.
+///
+/// Without a buffer, the cursor only measures the length of the written data.
+struct Cursor<'a> {
+	buf: Option<&'a mut [u8]>,
+	pos: usize,
+}
+
+impl Cursor<'_> {
+	fn bytes(&mut self, bytes: &[u8]) {
+		if let Some(buf) = &mut self.buf {
+			buf[self.pos..self.pos + bytes.len()].copy_from_slice(bytes);
+		}
+		self.pos += bytes.len();
+	}
+
+	fn align(&mut self) {
+		// The buffer is zeroed, so we only need to skip the padding.
+		self.pos = self.pos.next_multiple_of(4);
+	}
+
+	fn be32(&mut self, value: u32) {
+		self.bytes(&value.to_be_bytes());
+	}
+
+	fn be64(&mut self, value: u64) {
+		self.bytes(&value.to_be_bytes());
+	}
+
+	fn cells(&mut self, cells: usize, value: u64) {
+		match cells {
+			1 => self.be32(value.try_into().unwrap()),
+			2 => self.be64(value),
+			_ => unreachable!(),
+		}
+	}
+
+	fn prop(&mut self, nameoff: usize, value: &[u8]) {
+		self.be32(FDT_PROP);
+		self.be32(value.len().try_into().unwrap());
+		self.be32(nameoff.try_into().unwrap());
+		self.bytes(value);
+		self.align();
+	}
+
+	/// Writes a null-terminated, aligned string.
+	fn name(&mut self, args: fmt::Arguments<'_>) {
+		fmt::Write::write_fmt(self, args).unwrap();
+		self.pos += 1;
+		self.align();
+	}
+
+	/// Writes a null-terminated string to the strings block.
+	fn name_unaligned(&mut self, name: &str) {
+		self.bytes(name.as_bytes());
+		self.pos += 1;
+	}
+}
+
+impl fmt::Write for Cursor<'_> {
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		self.bytes(s.as_bytes());
+		Ok(())
+	}
 }
 
 impl FdtExt for fdt::Fdt<'_> {
@@ -47,4 +578,61 @@ impl FdtExt for fdt::Fdt<'_> {
 		self.find_module_start()
 			.or_else(|| self.find_linux_initrd())
 	}
+
+	fn log_additional_modules(&self) {
+		let Some(chosen) = self.find_node("/chosen") else {
+			return;
+		};
+		let kernel = self.find_kernel().map(<[u8]>::as_ptr);
+
+		let modules = chosen
+			.children()
+			.filter(|node| node.name.starts_with("module@"))
+			.filter(|node| {
+				let start = node.reg().and_then(|mut reg| reg.next());
+				start.map(|reg| reg.starting_address) != kernel
+			});
+
+		for module in modules {
+			let string = module
+				.property("bootargs")
+				.and_then(|bootargs| bootargs.as_str())
+				.unwrap_or_default();
+			info!("Forwarding {} ({string:?}) to the kernel", module.name);
+		}
+	}
+
+	fn module_regions(&self) -> impl Iterator<Item = Range<u64>> {
+		let kernel = self.find_kernel().map(<[u8]>::as_ptr);
+
+		self.find_node("/chosen")
+			.into_iter()
+			.flat_map(|chosen| chosen.children())
+			.filter(|node| node.name.starts_with("module@"))
+			.filter_map(|node| node.reg())
+			.flatten()
+			.filter(move |region| Some(region.starting_address) != kernel)
+			.filter_map(|region| {
+				let start = region.starting_address.expose_provenance() as u64;
+				let size = region.size? as u64;
+				Some(start..start + size)
+			})
+	}
+
+	fn memory_regions(&self) -> impl Iterator<Item = Range<u64>> {
+		self.find_node("/")
+			.unwrap()
+			.children()
+			.filter(|node| match node.property("device_type") {
+				Some(device_type) => device_type.as_str() == Some("memory"),
+				None => node.name.split('@').next() == Some("memory"),
+			})
+			.filter_map(|node| node.reg())
+			.flatten()
+			.filter_map(|region| {
+				let start = region.starting_address.expose_provenance() as u64;
+				let size = region.size? as u64;
+				Some(start..start + size)
+			})
+	}
 }
@@ -0,0 +1,36 @@
+//! The header fields of a flattened device tree.
+//!
+//! For reference, see <https://devicetree-specification.readthedocs.io/en/stable/flattened-format.html#header>.
+
+pub const TOTALSIZE: usize = 4;
+pub const OFF_DT_STRUCT: usize = 8;
+pub const OFF_DT_STRINGS: usize = 12;
+pub const OFF_MEM_RSVMAP: usize = 16;
+#[cfg_attr(
+	not(all(
+		target_os = "none",
+		any(target_arch = "aarch64", target_arch = "riscv64")
+	)),
+	expect(dead_code)
+)]
+pub const SIZE_DT_STRINGS: usize = 32;
+#[cfg_attr(
+	not(all(
+		target_os = "none",
+		any(target_arch = "aarch64", target_arch = "riscv64")
+	)),
+	expect(dead_code)
+)]
+pub const SIZE_DT_STRUCT: usize = 36;
+
+/// Reads the big-endian 32-bit value at `offset` of `blob`.
+pub fn read_be32(blob: &[u8], offset: usize) -> Option<usize> {
+	let bytes = blob.get(offset..)?.first_chunk::<4>()?;
+	Some(u32::from_be_bytes(*bytes).try_into().unwrap())
+}
+
+/// Writes `value` as big-endian 32-bit value at `offset` of `blob`.
+pub fn write_be32(blob: &mut [u8], offset: usize, value: usize) {
+	let value = u32::try_from(value).unwrap();
+	blob[offset..offset + 4].copy_from_slice(&value.to_be_bytes());
+}
@@ -0,0 +1,95 @@
+//! Linear framebuffers set up by the firmware.
+
+#![cfg_attr(feature = "pvh", expect(dead_code))]
+
+pub mod console;
+mod font;
+
+use core::fmt;
+
+/// A linear framebuffer.
+#[derive(Clone, Copy, Debug)]
+pub struct Framebuffer {
+	/// The physical address of the first pixel.
+	pub address: u64,
+	pub width: u32,
+	pub height: u32,
+	/// The number of bytes per line.
+	pub stride: u32,
+	pub format: PixelFormat,
+}
+
+impl Framebuffer {
+	/// Returns the size of the framebuffer in bytes.
+	pub fn size(&self) -> u64 {
+		u64::from(self.stride) * u64::from(self.height)
+	}
+}
+
+impl fmt::Display for Framebuffer {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let Self {
+			address,
+			width,
+			height,
+			stride,
+			format,
+		} = self;
+		write!(
+			f,
+			"{width}x{height} {format} at {address:#x}, stride = {stride}"
+		)
+	}
+}
+
+/// The pixel formats of the `simple-framebuffer` device tree binding that we support.
+///
+/// The names list the channels from the most significant to the least significant bits of a little-endian pixel.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PixelFormat {
+	R5G6B5,
+	R8G8B8,
+	X8R8G8B8,
+	X8B8G8R8,
+}
+
+impl PixelFormat {
+	/// Returns the pixel format with direct RGB color.
+	///
+	/// `rgb` contains the position and size in bits of the red, green, and blue fields.
+	/// Returns `None` if the pixel format is not supported.
+	pub fn from_rgb(bpp: u8, rgb: [(u8, u8); 3]) -> Option<Self> {
+		match (bpp, rgb) {
+			(16, [(11, 5), (5, 6), (0, 5)]) => Some(Self::R5G6B5),
+			(24, [(16, 8), (8, 8), (0, 8)]) => Some(Self::R8G8B8),
+			(32, [(16, 8), (8, 8), (0, 8)]) => Some(Self::X8R8G8B8),
+			(32, [(0, 8), (8, 8), (16, 8)]) => Some(Self::X8B8G8R8),
+			_ => None,
+		}
+	}
+
+	/// Returns the number of bytes per pixel.
+	pub fn bytes_per_pixel(self) -> usize {
+		match self {
+			Self::R5G6B5 => 2,
+			Self::R8G8B8 => 3,
+			Self::X8R8G8B8 | Self::X8B8G8R8 => 4,
+		}
+	}
+
+	/// Returns the name of the pixel format in the `simple-framebuffer` device tree binding.
+	pub fn name(self) -> &'static str {
+		match self {
+			Self::R5G6B5 => "r5g6b5",
+			Self::R8G8B8 => "r8g8b8",
+			Self::X8R8G8B8 => "x8r8g8b8",
+			Self::X8B8G8R8 => "x8b8g8r8",
+		}
+	}
+}
+
+impl fmt::Display for PixelFormat {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(self.name())
+	}
+}
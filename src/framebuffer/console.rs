@@ -0,0 +1,137 @@
+//! A text console on a linear framebuffer.
+//!
+//! The framebuffer console is enabled by setting `LOADER_FRAMEBUFFER_CONSOLE` to a non-empty value at build time.
+//! It renders the loader's output with the built-in [`font`] and scrolls when reaching the bottom of the screen.
+
+use core::ops::Range;
+use core::ptr;
+
+use super::{Framebuffer, PixelFormat, font};
+
+/// The foreground color as RGB.
+const FOREGROUND: [u8; 3] = [0xaa, 0xaa, 0xaa];
+
+pub fn enabled() -> bool {
+	option_env!("LOADER_FRAMEBUFFER_CONSOLE").is_some_and(|val| !val.is_empty())
+}
+
+pub struct FramebufferConsole {
+	framebuffer: Framebuffer,
+	/// The foreground color in the framebuffer's pixel format.
+	foreground: [u8; 4],
+	column: usize,
+	row: usize,
+}
+
+impl FramebufferConsole {
+	/// Creates a console on `framebuffer` and clears the screen.
+	///
+	/// # Safety
+	///
+	/// `framebuffer` must be identity-mapped and must not be accessed otherwise.
+	pub unsafe fn new(framebuffer: Framebuffer) -> Self {
+		let mut console = Self {
+			framebuffer,
+			foreground: framebuffer.format.encode(FOREGROUND),
+			column: 0,
+			row: 0,
+		};
+		console.clear(0..console.rows());
+		console
+	}
+
+	pub fn write_bytes(&mut self, bytes: &[u8]) {
+		for byte in bytes {
+			match byte {
+				b'\n' => self.new_line(),
+				b'\r' => self.column = 0,
+				_ => {
+					if self.column == self.columns() {
+						self.new_line();
+					}
+					self.draw_glyph(*byte);
+					self.column += 1;
+				}
+			}
+		}
+	}
+
+	fn columns(&self) -> usize {
+		usize::try_from(self.framebuffer.width).unwrap() / font::WIDTH
+	}
+
+	fn rows(&self) -> usize {
+		usize::try_from(self.framebuffer.height).unwrap() / font::HEIGHT
+	}
+
+	fn base(&self) -> *mut u8 {
+		ptr::with_exposed_provenance_mut(self.framebuffer.address.try_into().unwrap())
+	}
+
+	fn stride(&self) -> usize {
+		self.framebuffer.stride.try_into().unwrap()
+	}
+
+	fn new_line(&mut self) {
+		self.column = 0;
+		if self.row + 1 < self.rows() {
+			self.row += 1;
+			return;
+		}
+
+		// Scroll up by one row of text.
+		let row_size = font::HEIGHT * self.stride();
+		let len = (self.rows() - 1) * row_size;
+		unsafe {
+			ptr::copy(self.base().add(row_size), self.base(), len);
+		}
+		self.clear(self.row..self.row + 1);
+	}
+
+	fn clear(&mut self, rows: Range<usize>) {
+		let row_size = font::HEIGHT * self.stride();
+		unsafe {
+			ptr::write_bytes(
+				self.base().add(rows.start * row_size),
+				0,
+				rows.len() * row_size,
+			);
+		}
+	}
+
+	fn draw_glyph(&mut self, c: u8) {
+		let bytes_per_pixel = self.framebuffer.format.bytes_per_pixel();
+		let x = self.column * font::WIDTH;
+		let y = self.row * font::HEIGHT;
+
+		for (dy, bits) in font::glyph(c).iter().enumerate() {
+			let line = unsafe { self.base().add((y + dy) * self.stride()) };
+			for dx in 0..font::WIDTH {
+				let pixel = if bits & (0x80 >> dx) != 0 {
+					self.foreground
+				} else {
+					[0; 4]
+				};
+				unsafe {
+					let dst = line.add((x + dx) * bytes_per_pixel);
+					for (i, byte) in pixel[..bytes_per_pixel].iter().enumerate() {
+						dst.add(i).write_volatile(*byte);
+					}
+				}
+			}
+		}
+	}
+}
+
+impl PixelFormat {
+	/// Encodes `rgb` as a little-endian pixel in this format.
+	fn encode(self, [r, g, b]: [u8; 3]) -> [u8; 4] {
+		let [r, g, b] = [u32::from(r), u32::from(g), u32::from(b)];
+		let pixel = match self {
+			Self::R5G6B5 => (r >> 3) << 11 | (g >> 2) << 5 | b >> 3,
+			Self::R8G8B8 | Self::X8R8G8B8 => r << 16 | g << 8 | b,
+			Self::X8B8G8R8 => b << 16 | g << 8 | r,
+		};
+		pixel.to_le_bytes()
+	}
+}
@@ -0,0 +1,101 @@
+//! SHA-256 pinning of Hermit applications.
+//!
+//! The expected SHA-256 digest of the application image can be set
+//! at build time through the `LOADER_APP_SHA256` environment variable and
+//! at runtime through the `loader.app-sha256=<DIGEST>` bootargs option, both as 64 hexadecimal digits.
+//! If both are set, the application has to match both.
+//!
+//! The digest is computed over the image as provided, including any compression or appended signature,
+//! so that it matches the output of `sha256sum`.
+
+use log::{error, info};
+use sha2::{Digest, Sha256};
+
+use crate::hex::{self, Hex};
+
+/// The bootargs option for setting the expected digest.
+const BOOTARGS_OPTION: &str = "loader.app-sha256=";
+
+/// The name of the device tree property in `/chosen` that holds the digest.
+pub const FDT_PROPERTY: &str = "hermit,app-sha256";
+
+/// The expected digest from `LOADER_APP_SHA256`, which is validated at compile time.
+const EXPECTED: Option<[u8; 32]> = match option_env!("LOADER_APP_SHA256") {
+	Some(hex) => {
+		let mut digest = [0; 32];
+		if hex::decode(hex, &mut digest).is_none() {
+			panic!("LOADER_APP_SHA256 is not a hexadecimal SHA-256 digest");
+		}
+		Some(digest)
+	}
+	None => None,
+};
+
+/// Computes the SHA-256 digest of `image` and verifies it against the expected digests.
+///
+/// `bootargs` are searched for the `loader.app-sha256=` option up to the first `--`.
+///
+/// # Panics
+///
+/// Panics if the digest does not match or if the digest from the bootargs is invalid.
+pub fn verify(image: &[u8], bootargs: Option<&str>) -> [u8; 32] {
+	let digest: [u8; 32] = Sha256::digest(image).into();
+	info!("SHA-256 of the Hermit application: {}", Hex(&digest));
+
+	let from_bootargs = bootargs.and_then(find_option).map(|hex| {
+		let mut expected = [0; 32];
+		if hex::decode(hex, &mut expected).is_none() {
+			error!("The expected SHA-256 digest from bootargs is invalid: {hex}");
+			panic!("refusing to boot Hermit application without valid expected digest");
+		}
+		expected
+	});
+
+	let expected = [("LOADER_APP_SHA256", EXPECTED), ("bootargs", from_bootargs)];
+
+	for (source, expected) in expected {
+		let Some(expected) = expected else {
+			continue;
+		};
+
+		if digest != expected {
+			let expected = Hex(&expected);
+			error!(
+				"The SHA-256 digest of the Hermit application does not match {source}: expected {expected}"
+			);
+			panic!("refusing to boot Hermit application with unexpected digest");
+		}
+
+		info!("The SHA-256 digest of the Hermit application matches {source}");
+	}
+
+	digest
+}
+
+fn find_option(bootargs: &str) -> Option<&str> {
+	bootargs
+		.split_whitespace()
+		.take_while(|arg| *arg != "--")
+		.find_map(|arg| arg.strip_prefix(BOOTARGS_OPTION))
+}
+
+#[cfg(all(test, not(target_os = "none")))]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn find_option() {
+		assert_eq!(super::find_option(""), None);
+		assert_eq!(super::find_option("-freq 1000"), None);
+		assert_eq!(
+			super::find_option("-freq 1000 loader.app-sha256=abc -- arg"),
+			Some("abc")
+		);
+		assert_eq!(
+			super::find_option("loader.app-sha256=abc loader.app-sha256=def"),
+			Some("abc")
+		);
+		assert_eq!(super::find_option("-- loader.app-sha256=abc"), None);
+		assert_eq!(super::find_option("loader.app-sha256 abc"), None);
+	}
+}
@@ -0,0 +1,71 @@
+//! Hexadecimal encoding of binary data.
+
+use core::fmt;
+
+/// Decodes `hex` into `out`, which has to be exactly half as long.
+///
+/// This is a `const fn`, so that build-time configuration can be validated at compile time.
+pub const fn decode(hex: &str, out: &mut [u8]) -> Option<()> {
+	const fn digit(digit: u8) -> Option<u8> {
+		match digit {
+			b'0'..=b'9' => Some(digit - b'0'),
+			b'a'..=b'f' => Some(digit - b'a' + 10),
+			b'A'..=b'F' => Some(digit - b'A' + 10),
+			_ => None,
+		}
+	}
+
+	let hex = hex.as_bytes();
+	if hex.len() != out.len() * 2 {
+		return None;
+	}
+
+	let mut i = 0;
+	while i < out.len() {
+		let (Some(high), Some(low)) = (digit(hex[2 * i]), digit(hex[2 * i + 1])) else {
+			return None;
+		};
+		out[i] = high << 4 | low;
+		i += 1;
+	}
+
+	Some(())
+}
+
+/// Displays bytes as lowercase hexadecimal digits.
+pub struct Hex<'a>(pub &'a [u8]);
+
+impl fmt::Display for Hex<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		for byte in self.0 {
+			write!(f, "{byte:02x}")?;
+		}
+		Ok(())
+	}
+}
+
+#[cfg(all(test, not(target_os = "none")))]
+mod tests {
+	use alloc::format;
+
+	use super::*;
+
+	#[test]
+	fn decode() {
+		let mut out = [0; 4];
+		assert_eq!(super::decode("0123abCD", &mut out), Some(()));
+		assert_eq!(out, [0x01, 0x23, 0xab, 0xcd]);
+
+		assert_eq!(super::decode("", &mut []), Some(()));
+		assert_eq!(super::decode("0123ab", &mut out), None);
+		assert_eq!(super::decode("0123abcdef", &mut out), None);
+		assert_eq!(super::decode("0123abcg", &mut out), None);
+		assert_eq!(super::decode("0123ab c", &mut out), None);
+	}
+
+	#[test]
+	fn hex() {
+		assert_eq!(format!("{}", Hex(&[0x01, 0x23, 0xab, 0xcd])), "0123abcd");
+		assert_eq!(format!("{}", Hex(&[])), "");
+	}
+}
@@ -0,0 +1,34 @@
+//! Kernel address space layout randomization (KASLR) for relocatable kernels.
+//!
+//! KASLR is enabled by setting `LOADER_KASLR` to a non-empty value at build time.
+//! The kernel is then placed at a random [`ALIGN`]-aligned address.
+//!
+//! Since the kernel only uses memory above its image,
+//! the randomization window is limited to a quarter of the free memory.
+
+use log::{info, warn};
+
+use crate::random::{self, Source};
+
+/// The alignment of randomized kernel addresses.
+pub const ALIGN: u64 = 0x20_0000;
+
+pub fn enabled() -> bool {
+	option_env!("LOADER_KASLR").is_some_and(|val| !val.is_empty())
+}
+
+/// Returns a random [`ALIGN`]-aligned offset for placing the kernel.
+///
+/// `free` is the number of bytes of free memory after the kernel that the offset may use.
+pub fn random_offset(free: u64) -> u64 {
+	let slots = free / 4 / ALIGN + 1;
+
+	let (random, source) = random::random_u64();
+	if source == Source::Timer {
+		warn!("KASLR: No hardware entropy source found, falling back to the timer");
+	}
+
+	let offset = random % slots * ALIGN;
+	info!("KASLR: Chose offset {offset:#x} of {slots} slots (entropy from {source})");
+	offset
+}
@@ -54,10 +54,7 @@ struct Logger;
 
 impl log::Log for Logger {
 	fn enabled(&self, metadata: &Metadata<'_>) -> bool {
-		let level = option_env!("LOADER_LOG")
-			.map(|var| var.parse().unwrap())
-			.unwrap_or(Level::Info);
-		metadata.level() <= level
+		metadata.level() <= log::max_level()
 	}
 
 	fn log(&self, record: &Record<'_>) {
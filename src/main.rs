@@ -12,17 +12,34 @@ use hermit_entry::boot_info::{BootInfo, RawBootInfo};
 mod macros;
 
 mod arch;
+mod bootargs;
 mod bump_allocator;
+mod compression;
 #[cfg(any(target_os = "uefi", target_arch = "x86_64"))]
 mod fdt;
-#[cfg(any(target_arch = "aarch64", target_arch = "riscv64"))]
+#[cfg(all(
+	target_os = "none",
+	any(target_arch = "aarch64", target_arch = "riscv64")
+))]
 mod fdt_ext;
+mod fdt_header;
+#[cfg(any(target_os = "uefi", target_arch = "x86_64"))]
+mod framebuffer;
+mod hash;
+mod hex;
+mod kaslr;
 mod log;
 mod os;
-#[cfg(any(target_arch = "aarch64", target_arch = "riscv64"))]
+mod random;
+mod reserved_memory;
+mod signature;
+#[cfg(all(
+	target_os = "none",
+	any(target_arch = "aarch64", target_arch = "riscv64")
+))]
 mod stack;
+mod time;
 
-#[cfg(any(target_os = "uefi", all(target_arch = "x86_64", target_os = "none")))]
 extern crate alloc;
 
 trait BootInfoExt {
@@ -2,6 +2,7 @@
 
 mod bootstrap;
 
+use core::mem::MaybeUninit;
 use core::ptr;
 use core::ptr::NonNull;
 
@@ -17,12 +18,16 @@ struct GlobalAllocator {
 	///
 	/// It allows allocations before the heap has been initalized.
 	bootstrap_allocator: Option<BootstrapAllocator<BumpAllocator>>,
+
+	/// The heap, which is only initialized if the bootstrap allocator does not suffice.
+	heap: Option<BumpAllocator>,
 }
 
 impl GlobalAllocator {
 	const fn empty() -> Self {
 		Self {
 			bootstrap_allocator: None,
+			heap: None,
 		}
 	}
 
@@ -37,6 +42,7 @@ impl GlobalAllocator {
 		self.bootstrap_allocator
 			.get_or_insert_with(Default::default)
 			.allocate(layout)
+			.or_else(|err| self.heap.as_ref().ok_or(err)?.allocate(layout))
 			// FIXME: Use NonNull::as_mut_ptr once `slice_ptr_get` is stabilized
 			// https://github.com/rust-lang/rust/issues/74265
 			.map(|ptr| NonNull::new(ptr.as_ptr() as *mut u8).unwrap())
@@ -45,9 +51,14 @@ impl GlobalAllocator {
 	unsafe fn deallocate(&mut self, ptr: NonNull<u8>, layout: Layout) {
 		let layout = Self::align_layout(layout);
 		let bootstrap_allocator = self.bootstrap_allocator.as_ref().unwrap();
-		assert!(bootstrap_allocator.manages(ptr));
-		unsafe {
-			bootstrap_allocator.deallocate(ptr, layout);
+		if bootstrap_allocator.manages(ptr) {
+			unsafe {
+				bootstrap_allocator.deallocate(ptr, layout);
+			}
+		} else {
+			unsafe {
+				self.heap.as_ref().unwrap().deallocate(ptr, layout);
+			}
 		}
 	}
 }
@@ -84,6 +95,13 @@ unsafe impl GlobalAlloc for LockedAllocator {
 #[global_allocator]
 static ALLOCATOR: LockedAllocator = LockedAllocator::empty();
 
+/// Provides the global allocator with `mem` for allocations that exceed the bootstrap allocator.
+pub fn init_heap(mem: &'static mut [MaybeUninit<u8>]) {
+	let mut allocator = ALLOCATOR.0.lock();
+	assert!(allocator.heap.is_none());
+	allocator.heap = Some(BumpAllocator::from(mem));
+}
+
 #[cfg(all(test, not(target_os = "none")))]
 mod tests {
 	use core::mem;
@@ -1,27 +1,66 @@
 use core::fmt;
 
 use crate::arch;
+#[cfg(target_arch = "x86_64")]
+use crate::framebuffer::console::FramebufferConsole;
 
 pub struct Console {
 	console: Option<arch::Console>,
+	#[cfg(target_arch = "x86_64")]
+	framebuffer: Option<FramebufferConsole>,
 }
 
 impl Console {
 	const fn new() -> Self {
-		Self { console: None }
+		Self {
+			console: None,
+			#[cfg(target_arch = "x86_64")]
+			framebuffer: None,
+		}
+	}
+
+	/// Mirrors all further output to `framebuffer`.
+	#[cfg(target_arch = "x86_64")]
+	pub fn set_framebuffer(&mut self, framebuffer: FramebufferConsole) {
+		self.framebuffer = Some(framebuffer);
 	}
 
 	#[cfg(target_arch = "aarch64")]
 	pub fn get(&mut self) -> &mut arch::Console {
-		self.console.get_or_insert_with(arch::Console::default)
+		self.console().unwrap()
+	}
+
+	/// Returns the console, creating it on first use.
+	fn console(&mut self) -> Option<&mut arch::Console> {
+		// Creating the console probes the device tree, which may log.
+		// Output that is written while the console is being created is discarded.
+		#[cfg(target_arch = "aarch64")]
+		if self.console.is_none() {
+			use core::sync::atomic::{AtomicBool, Ordering};
+
+			static CREATING: AtomicBool = AtomicBool::new(false);
+
+			if CREATING.swap(true, Ordering::Relaxed) {
+				return None;
+			}
+			let console = arch::Console::default();
+			CREATING.store(false, Ordering::Relaxed);
+			self.console = Some(console);
+		}
+
+		Some(self.console.get_or_insert_with(arch::Console::default))
 	}
 }
 
 impl fmt::Write for Console {
 	fn write_str(&mut self, s: &str) -> fmt::Result {
-		self.console
-			.get_or_insert_with(arch::Console::default)
-			.write_bytes(s.as_bytes());
+		if let Some(console) = self.console() {
+			console.write_bytes(s.as_bytes());
+		}
+		#[cfg(target_arch = "x86_64")]
+		if let Some(framebuffer) = &mut self.framebuffer {
+			framebuffer.write_bytes(s.as_bytes());
+		}
 		Ok(())
 	}
 }
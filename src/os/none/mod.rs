@@ -11,7 +11,9 @@ use hermit_entry::elf::KernelObject;
 use log::info;
 
 pub use self::console::CONSOLE;
-use crate::arch;
+use crate::bootargs::Bootargs;
+use crate::compression::Compression;
+use crate::{arch, hash, kaslr, signature};
 
 /// Entry Point of the BIOS Loader
 /// (called from entry.asm or entry.rs)
@@ -20,12 +22,34 @@ pub(crate) unsafe extern "C" fn loader_main() -> ! {
 	let loader_end = elf_symbols::executable_end();
 	info!("Loader: [{loader_start:p} - {loader_end:p}]");
 
+	let mut bootargs = Bootargs::new();
+	if let Some(command_line) = arch::command_line() {
+		bootargs.layer("command line", command_line);
+	}
+	let bootargs = bootargs.finish().map(|bootargs| &*bootargs.leak());
+
 	let kernel = arch::find_kernel();
+	let app_sha256 = hash::verify(kernel, bootargs);
+	let kernel = signature::verify(kernel, None);
+	let kernel = match Compression::detect(kernel) {
+		Some(compression) => decompress(compression, kernel),
+		None => kernel,
+	};
 	let kernel = KernelObject::parse(kernel).unwrap();
 
 	let mem_size = kernel.mem_size();
-	let kernel_addr = unsafe { arch::get_memory(mem_size as u64) };
-	let kernel_addr = kernel.start_addr().unwrap_or(kernel_addr);
+	let kernel_addr = match kernel.start_addr() {
+		None if kaslr::enabled() => {
+			let offset = kaslr::random_offset(arch::free_memory_after(mem_size as u64));
+			let kernel_addr = unsafe { arch::get_memory(offset + mem_size as u64) } + offset;
+			info!("KASLR: Loading kernel at {kernel_addr:#x}");
+			kernel_addr
+		}
+		start_addr => {
+			let kernel_addr = unsafe { arch::get_memory(mem_size as u64) };
+			start_addr.unwrap_or(kernel_addr)
+		}
+	};
 	let memory = unsafe {
 		slice::from_raw_parts_mut(
 			ptr::with_exposed_provenance_mut::<MaybeUninit<u8>>(kernel_addr as usize),
@@ -35,7 +59,38 @@ pub(crate) unsafe extern "C" fn loader_main() -> ! {
 
 	let kernel_info = kernel.load_kernel(memory, memory.as_ptr() as u64);
 
-	unsafe { arch::boot_kernel(kernel_info) }
+	unsafe { arch::boot_kernel(kernel_info, bootargs, app_sha256) }
+}
+
+/// Decompresses the Hermit application into physical memory obtained from [`arch::get_memory`].
+fn decompress(compression: Compression, image: &[u8]) -> &'static [u8] {
+	let scratch_size = compression.scratch_size();
+	if scratch_size > 0 {
+		let scratch_addr = unsafe { arch::get_memory(scratch_size as u64) };
+		let scratch = unsafe {
+			slice::from_raw_parts_mut(
+				ptr::with_exposed_provenance_mut::<MaybeUninit<u8>>(scratch_addr as usize),
+				scratch_size,
+			)
+		};
+		allocator::init_heap(scratch);
+	}
+
+	let size = compression
+		.decompressed_size(image)
+		.unwrap_or_else(|err| panic!("could not decompress the Hermit application: {err}"));
+	let addr = unsafe { arch::get_memory(size as u64) };
+	let memory = unsafe {
+		let ptr = ptr::with_exposed_provenance_mut::<u8>(addr as usize);
+		ptr::write_bytes(ptr, 0, size);
+		slice::from_raw_parts_mut(ptr, size)
+	};
+
+	compression
+		.decompress_into(image, memory)
+		.unwrap_or_else(|err| panic!("could not decompress the Hermit application: {err}"));
+
+	memory
 }
 
 #[panic_handler]
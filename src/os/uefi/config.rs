@@ -0,0 +1,183 @@
+//! The loader configuration file `loader.conf`.
+//!
+//! Each line is empty, a comment starting with `#`, or a `key = value` pair.
+//! Supported keys are:
+//!
+//! - `app`: the path of the Hermit application, which skips the boot menu,
+//! - `bootargs`: the bootargs, which replace any bootargs file,
+//! - `log_level`: the log level of the loader (`off`, `error`, `warn`, `info`, `debug`, or `trace`),
+//! - `module`: the path of an additional module (may be repeated),
+//! - `resolution`: the GOP resolution as `<WIDTH>x<HEIGHT>`,
+//! - `serial_port`: the serial port base for the kernel,
+//! - `timeout`: the boot menu timeout in seconds.
+//!
+//! Invalid lines are reported and ignored.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use anyhow::{Context, anyhow, bail};
+use hermit_entry::boot_info::SerialPortBase;
+use log::{LevelFilter, error, info};
+use uefi::CString16;
+
+#[derive(Default, Debug)]
+pub struct Config {
+	pub app: Option<CString16>,
+	pub bootargs: Option<String>,
+	pub log_level: Option<LevelFilter>,
+	pub modules: Vec<CString16>,
+	pub resolution: Option<(u32, u32)>,
+	pub serial_port: Option<SerialPortBase>,
+	pub timeout: Option<Duration>,
+}
+
+impl Config {
+	/// Parses the configuration file `s`, which was read from `path`.
+	pub fn parse(path: &str, s: &str) -> Self {
+		let mut config = Self::default();
+
+		for (i, line) in s.lines().enumerate() {
+			let line = line.trim();
+			if line.is_empty() || line.starts_with('#') {
+				continue;
+			}
+
+			if let Err(err) = config.parse_line(line) {
+				let line = i + 1;
+				error!("{path}:{line}: {err:#}");
+			}
+		}
+
+		info!("Loader configuration: {config:?}");
+		config
+	}
+
+	fn parse_line(&mut self, line: &str) -> anyhow::Result<()> {
+		let (key, value) = line
+			.split_once('=')
+			.ok_or_else(|| anyhow!("expected `key = value`"))?;
+		let (key, value) = (key.trim(), value.trim());
+
+		match key {
+			"app" => self.app = Some(parse_path(value)?),
+			"bootargs" => self.bootargs = Some(value.into()),
+			"log_level" => {
+				let level = value
+					.parse()
+					.map_err(|_| anyhow!("invalid log level `{value}`"))?;
+				self.log_level = Some(level);
+			}
+			"module" => self.modules.push(parse_path(value)?),
+			"resolution" => {
+				let resolution = value
+					.split_once('x')
+					.and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)))
+					.ok_or_else(|| anyhow!("invalid resolution `{value}`"))?;
+				self.resolution = Some(resolution);
+			}
+			"serial_port" => {
+				// The serial port base is an I/O port on x86_64 and an address elsewhere.
+				#[cfg_attr(not(target_arch = "x86_64"), expect(clippy::useless_conversion))]
+				let port = parse_int(value)?
+					.try_into()
+					.ok()
+					.and_then(SerialPortBase::new)
+					.ok_or_else(|| anyhow!("invalid serial port `{value}`"))?;
+				self.serial_port = Some(port);
+			}
+			"timeout" => self.timeout = Some(Duration::from_secs(parse_int(value)?)),
+			_ => bail!("unknown key `{key}`"),
+		}
+
+		Ok(())
+	}
+}
+
+fn parse_path(value: &str) -> anyhow::Result<CString16> {
+	CString16::try_from(value).map_err(|err| anyhow!("invalid path `{value}`: {err:?}"))
+}
+
+fn parse_int(value: &str) -> anyhow::Result<u64> {
+	let result = match value.strip_prefix("0x") {
+		Some(hex) => u64::from_str_radix(hex, 16),
+		None => value.parse(),
+	};
+	result.with_context(|| format!("invalid number `{value}`"))
+}
+
+#[cfg(all(test, not(target_os = "none")))]
+mod tests {
+	use super::*;
+
+	fn path(s: &str) -> CString16 {
+		CString16::try_from(s).unwrap()
+	}
+
+	#[test]
+	fn parse() {
+		let config = Config::parse(
+			"loader.conf",
+			"# Hermit loader configuration
+
+			app = \\EFI\\hermit\\app
+			bootargs=  -freq 1000 -- arg \t
+			log_level = debug
+			module = \\modules\\a
+			module = \\modules\\b
+			resolution = 1920x1080
+			serial_port = 0x3f8
+			timeout = 5
+			",
+		);
+
+		assert_eq!(config.app, Some(path("\\EFI\\hermit\\app")));
+		assert_eq!(config.bootargs.as_deref(), Some("-freq 1000 -- arg"));
+		assert_eq!(config.log_level, Some(LevelFilter::Debug));
+		assert_eq!(config.modules, [path("\\modules\\a"), path("\\modules\\b")]);
+		assert_eq!(config.resolution, Some((1920, 1080)));
+		assert_eq!(config.serial_port.map(SerialPortBase::get), Some(0x3f8));
+		assert_eq!(config.timeout, Some(Duration::from_secs(5)));
+	}
+
+	#[test]
+	fn parse_invalid_lines() {
+		let config = Config::parse(
+			"loader.conf",
+			"timeout
+			colour = blue
+			timeout = 3",
+		);
+
+		assert_eq!(config.timeout, Some(Duration::from_secs(3)));
+		assert_eq!(config.app, None);
+	}
+
+	#[test]
+	fn parse_line() {
+		let mut config = Config::default();
+
+		let err = config.parse_line("timeout").unwrap_err();
+		assert_eq!(format!("{err}"), "expected `key = value`");
+
+		let err = config.parse_line("colour = blue").unwrap_err();
+		assert_eq!(format!("{err}"), "unknown key `colour`");
+
+		assert!(config.parse_line("resolution = 1920").is_err());
+		assert!(config.parse_line("log_level = loud").is_err());
+		assert!(config.parse_line("serial_port = 0").is_err());
+		assert!(config.parse_line("timeout = soon").is_err());
+	}
+
+	#[test]
+	fn parse_int() {
+		assert_eq!(super::parse_int("42").unwrap(), 42);
+		assert_eq!(super::parse_int("0x3f8").unwrap(), 0x3f8);
+		assert!(super::parse_int("").is_err());
+		assert!(super::parse_int("0x").is_err());
+		assert!(super::parse_int("3f8").is_err());
+		assert!(super::parse_int("-1").is_err());
+	}
+}
@@ -7,11 +7,18 @@ use uefi::Event;
 use uefi::boot::{EventType, Tpl};
 
 use crate::arch;
+use crate::framebuffer::Framebuffer;
+use crate::framebuffer::console::FramebufferConsole;
 
 pub enum Console {
 	None,
-	BootServices,
-	Native { console: arch::Console },
+	BootServices {
+		framebuffer: Option<Framebuffer>,
+	},
+	Native {
+		console: arch::Console,
+		framebuffer: Option<FramebufferConsole>,
+	},
 }
 
 impl Console {
@@ -19,10 +26,27 @@ impl Console {
 		Self::None
 	}
 
+	/// Mirrors all output to `framebuffer` after exiting boot services.
+	///
+	/// Until then, the UEFI console already draws onto the framebuffer.
+	pub fn set_framebuffer(&mut self, framebuffer: Framebuffer) {
+		if matches!(self, Self::None) {
+			self.init();
+		}
+		let Self::BootServices { framebuffer: fb } = self else {
+			panic!("Boot services have already been exited");
+		};
+		*fb = Some(framebuffer);
+	}
+
 	fn exit_boot_services(&mut self) {
-		assert!(matches!(self, Self::BootServices { .. }));
+		let Self::BootServices { framebuffer } = *self else {
+			panic!("Boot services have already been exited");
+		};
 		*self = Self::Native {
 			console: arch::Console::default(),
+			framebuffer: framebuffer
+				.map(|framebuffer| unsafe { FramebufferConsole::new(framebuffer) }),
 		};
 	}
 
@@ -37,7 +61,7 @@ impl Console {
 			)
 			.unwrap();
 		}
-		*self = Console::BootServices;
+		*self = Console::BootServices { framebuffer: None };
 	}
 }
 
@@ -48,8 +72,18 @@ impl fmt::Write for Console {
 				self.init();
 				self.write_str(s)?;
 			}
-			Console::BootServices => uefi::system::with_stdout(|stdout| stdout.write_str(s))?,
-			Console::Native { console } => console.write_bytes(s.as_bytes()),
+			Console::BootServices { .. } => {
+				uefi::system::with_stdout(|stdout| stdout.write_str(s))?
+			}
+			Console::Native {
+				console,
+				framebuffer,
+			} => {
+				console.write_bytes(s.as_bytes());
+				if let Some(framebuffer) = framebuffer {
+					framebuffer.write_bytes(s.as_bytes());
+				}
+			}
 		}
 		Ok(())
 	}
@@ -0,0 +1,89 @@
+//! The framebuffer of the UEFI Graphics Output Protocol (GOP).
+
+use log::{info, warn};
+use uefi::boot::{self, OpenProtocolAttributes, OpenProtocolParams};
+use uefi::proto::console::gop::{self, GraphicsOutput, PixelBitmask};
+
+use crate::framebuffer::{Framebuffer, PixelFormat};
+
+/// Returns the GOP framebuffer after switching to `resolution`, if requested.
+pub fn framebuffer(resolution: Option<(u32, u32)>) -> Option<Framebuffer> {
+	let Ok(handle) = boot::get_handle_for_protocol::<GraphicsOutput>() else {
+		info!("No GOP found, booting without framebuffer");
+		return None;
+	};
+
+	// Opening the GOP exclusively would disconnect the UEFI console from it.
+	let params = OpenProtocolParams {
+		handle,
+		agent: boot::image_handle(),
+		controller: None,
+	};
+	let mut gop = match unsafe {
+		boot::open_protocol::<GraphicsOutput>(params, OpenProtocolAttributes::GetProtocol)
+	} {
+		Ok(gop) => gop,
+		Err(err) => {
+			warn!("Could not open GOP: {err}");
+			return None;
+		}
+	};
+
+	if let Some((width, height)) = resolution {
+		let resolution = (width.try_into().unwrap(), height.try_into().unwrap());
+		let mode = gop
+			.modes()
+			.find(|mode| mode.info().resolution() == resolution);
+		match mode {
+			Some(mode) => match gop.set_mode(&mode) {
+				Ok(()) => info!("Switched GOP to {width}x{height}"),
+				Err(err) => warn!("Could not switch GOP to {width}x{height}: {err}"),
+			},
+			None => warn!("GOP does not support {width}x{height}"),
+		}
+	}
+
+	let mode_info = gop.current_mode_info();
+	let format = match mode_info.pixel_format() {
+		gop::PixelFormat::Rgb => Some(PixelFormat::X8B8G8R8),
+		gop::PixelFormat::Bgr => Some(PixelFormat::X8R8G8B8),
+		gop::PixelFormat::Bitmask => mode_info.pixel_bitmask().and_then(pixel_format),
+		gop::PixelFormat::BltOnly => None,
+	};
+	let Some(format) = format else {
+		warn!(
+			"Unsupported GOP pixel format: {:?}",
+			mode_info.pixel_format()
+		);
+		return None;
+	};
+
+	let (width, height) = mode_info.resolution();
+	let stride = mode_info.stride() * format.bytes_per_pixel();
+	let address = gop.frame_buffer().as_mut_ptr().expose_provenance();
+	Some(Framebuffer {
+		address: address.try_into().unwrap(),
+		width: width.try_into().unwrap(),
+		height: height.try_into().unwrap(),
+		stride: stride.try_into().unwrap(),
+		format,
+	})
+}
+
+fn pixel_format(bitmask: PixelBitmask) -> Option<PixelFormat> {
+	let PixelBitmask {
+		red,
+		green,
+		blue,
+		reserved,
+	} = bitmask;
+
+	let bits = 32 - (red | green | blue | reserved).leading_zeros();
+	let bpp = u8::try_from(bits.next_multiple_of(8)).unwrap();
+	let field = |mask: u32| {
+		let position = u8::try_from(mask.trailing_zeros()).unwrap();
+		let size = u8::try_from(mask.count_ones()).unwrap();
+		(position, size)
+	};
+	PixelFormat::from_rgb(bpp, [field(red), field(green), field(blue)])
+}
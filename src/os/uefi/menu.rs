@@ -0,0 +1,103 @@
+//! Text-mode boot menu for selecting one of several Hermit applications.
+//!
+//! The menu uses the UEFI console, which firmware such as OVMF also multiplexes onto the serial port.
+
+use alloc::string::String;
+use core::time::Duration;
+
+use log::info;
+use uefi::proto::console::text::{Key, ScanCode};
+use uefi::{boot, system};
+
+/// The interval for polling the keyboard.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// The time to wait for input before booting the default entry.
+///
+/// This can be configured through `LOADER_MENU_TIMEOUT` in seconds at build time,
+/// which is validated at compile time.
+pub const TIMEOUT: Duration = match option_env!("LOADER_MENU_TIMEOUT") {
+	Some(secs) => match u64::from_str_radix(secs, 10) {
+		Ok(secs) => Duration::from_secs(secs),
+		Err(_) => panic!("LOADER_MENU_TIMEOUT is not a number of seconds"),
+	},
+	None => Duration::from_secs(5),
+};
+
+/// Lets the user select one of `entries` and returns its index.
+///
+/// `default` is selected if no key is pressed within `timeout`.
+pub fn select(entries: &[String], default: usize, timeout: Duration) -> usize {
+	assert!(default < entries.len());
+
+	if timeout.is_zero() {
+		return default;
+	}
+
+	println!();
+	println!("Hermit applications:");
+	for (i, entry) in entries.iter().enumerate() {
+		let marker = if i == default { '*' } else { ' ' };
+		println!("{marker} {}) {entry}", i + 1);
+	}
+	println!("Press a number or Up/Down to select an application and Enter to boot it.");
+
+	let mut selected = default;
+	let mut remaining = Some(timeout);
+	let mut shown_secs = None;
+
+	loop {
+		if let Some(key) = read_key() {
+			if remaining.take().is_some() {
+				println!();
+			}
+
+			match key {
+				Key::Printable(c) if char::from(c) == '\r' => break,
+				Key::Printable(c) => {
+					let index = char::from(c)
+						.to_digit(10)
+						.and_then(|digit| usize::try_from(digit).unwrap().checked_sub(1))
+						.filter(|index| *index < entries.len());
+					if let Some(index) = index {
+						selected = index;
+						break;
+					}
+				}
+				Key::Special(ScanCode::UP) => {
+					selected = selected.checked_sub(1).unwrap_or(entries.len() - 1);
+					println!("> {}", entries[selected]);
+				}
+				Key::Special(ScanCode::DOWN) => {
+					selected = (selected + 1) % entries.len();
+					println!("> {}", entries[selected]);
+				}
+				Key::Special(_) => {}
+			}
+		}
+
+		if let Some(remaining) = &mut remaining {
+			if remaining.is_zero() {
+				println!();
+				break;
+			}
+
+			let secs = remaining.as_secs() + u64::from(remaining.subsec_nanos() > 0);
+			if shown_secs != Some(secs) {
+				print!("\rBooting {} in {secs} s ", entries[default]);
+				shown_secs = Some(secs);
+			}
+
+			*remaining = remaining.saturating_sub(POLL_INTERVAL);
+		}
+
+		boot::stall(POLL_INTERVAL);
+	}
+
+	info!("Selected Hermit application {}", entries[selected]);
+	selected
+}
+
+fn read_key() -> Option<Key> {
+	system::with_stdin(|stdin| stdin.read_key().ok().flatten())
+}
@@ -1,10 +1,19 @@
 mod allocator;
+mod config;
 mod console;
+mod gop;
+mod menu;
+#[cfg(target_arch = "riscv64")]
+mod riscv;
+mod tpm;
 
+use alloc::borrow::Cow;
 use alloc::string::String;
 use alloc::vec::Vec;
+use alloc::{format, vec};
 use core::ffi::c_void;
 use core::mem::MaybeUninit;
+use core::time::Duration;
 use core::{ptr, slice};
 
 use align_address::Align;
@@ -16,14 +25,21 @@ use hermit_entry::elf::{KernelObject, LoadedKernel};
 use log::{error, info};
 use uefi::boot::{AllocateType, MemoryType, PAGE_SIZE, open_protocol_exclusive};
 use uefi::fs::{self, FileSystem, Path};
+use uefi::mem::memory_map::MemoryMap;
 use uefi::prelude::*;
 use uefi::proto::loaded_image::LoadedImage;
+use uefi::proto::rng::Rng;
 use uefi::table::cfg::ConfigTableEntry;
-use uefi::{CString16, Guid, guid};
+use uefi::{CStr16, CString16, Guid, guid};
 
+use self::config::Config;
 pub use self::console::CONSOLE;
+use self::tpm::Tpm;
+use crate::bootargs::Bootargs;
+use crate::compression::Compression;
 use crate::fdt::Fdt;
-use crate::{BootInfoExt, arch};
+use crate::reserved_memory::{Reservation, ReservedMemory};
+use crate::{BootInfoExt, arch, framebuffer, hash, kaslr, random, signature};
 
 // Entry Point of the Uefi Loader
 #[entry]
@@ -33,51 +49,230 @@ fn main() -> Status {
 
 	let kernel_args = KernelArguments::new().unwrap();
 	let mut esp = BootPartition::new().unwrap();
+	let config = esp.read_config();
+	if let Some(log_level) = config.log_level {
+		log::set_max_level(log_level);
+	}
 
-	let kernel_image = if let Some(path) = kernel_args
+	let app_path = match kernel_args
 		.as_ref()
 		.and_then(|arg| arg.initrd_path.as_ref())
 	{
-		esp.read_app_at(path.as_ref())
-			.expect("Could not open kernel image provided in initrd")
+		Some(path) => Some(path.clone()),
+		None => config
+			.app
+			.clone()
+			.filter(|path| esp.app_exists(path))
+			.or_else(|| esp.select_app(config.timeout.unwrap_or(menu::TIMEOUT))),
+	};
+
+	let (kernel_file, kernel_signature) = if let Some(path) = &app_path {
+		let kernel_file = esp
+			.read_app_at(path.as_ref())
+			.expect("Could not read the selected Hermit application");
+		let kernel_signature = esp.read_signature_at(with_suffix(path, ".sig").as_ref());
+		(kernel_file, kernel_signature)
 	} else {
-		esp.read_app()
+		(esp.read_app(), esp.read_signature())
+	};
+
+	let mut bootargs = Bootargs::new();
+	if let Some(config_args) = &config.bootargs {
+		bootargs.layer("loader.conf", config_args);
+	} else {
+		let file_args = match &app_path {
+			Some(path) => esp
+				.read_bootargs_at(with_suffix(path, ".bootargs").as_ref())
+				.or_else(|| esp.read_bootargs()),
+			None => esp.read_bootargs(),
+		};
+		if let Some(file_args) = file_args {
+			bootargs.layer("bootargs file", &file_args);
+		}
+	}
+	if let Some(kernel_args) = kernel_args {
+		bootargs.layer("load options", &kernel_args.hermit_args);
+	}
+	let bootargs = bootargs.finish();
+
+	let app_sha256 = hash::verify(&kernel_file, bootargs.as_deref());
+
+	let mut tpm = Tpm::open();
+	if let Some(tpm) = &mut tpm {
+		tpm.measure(tpm::PCR_FILES, "Hermit application", &kernel_file);
+	}
+
+	let kernel_image = signature::verify(&kernel_file, kernel_signature.as_deref());
+	let kernel_image = match Compression::detect(kernel_image) {
+		Some(compression) => Cow::Owned(decompress(compression, kernel_image)),
+		None => Cow::Borrowed(kernel_image),
 	};
 	let kernel = KernelObject::parse(&kernel_image).unwrap();
 
-	let kernel_memory = alloc_page_slice(kernel.mem_size()).unwrap();
+	let kernel_memory = if kaslr::enabled() {
+		alloc_random_page_slice(kernel.mem_size()).unwrap()
+	} else {
+		alloc_page_slice(kernel.mem_size()).unwrap()
+	};
 	let kernel_memory = &mut kernel_memory[..kernel.mem_size()];
 
 	let kernel_info = kernel.load_kernel(kernel_memory, kernel_memory.as_ptr() as u64);
 
 	let rsdp = rsdp();
+	#[cfg(not(target_arch = "x86_64"))]
+	let dtb = device_tree();
+	#[cfg(not(target_arch = "x86_64"))]
+	match dtb {
+		Some(dtb) => info!("Found device tree at {:p}", dtb.as_ptr()),
+		None => info!("Device tree not found"),
+	}
 
 	drop(kernel_image);
+	drop(kernel_file);
 
-	let mut fdt = Fdt::new("uefi")
-		.unwrap()
-		.rsdp(u64::try_from(rsdp.expose_provenance()).unwrap())
-		.unwrap();
+	let cc_blob = detect_cc_blob();
+	let mut modules = esp.read_modules();
+	modules.extend(
+		config
+			.modules
+			.iter()
+			.filter_map(|path| esp.read_module_at(path)),
+	);
+
+	let loader = {
+		let loaded_image = open_protocol_exclusive::<LoadedImage>(boot::image_handle()).unwrap();
+		let (image_base, image_size) = loaded_image.info();
+		let start = u64::try_from(image_base.expose_provenance()).unwrap();
+		ReservedMemory::new(
+			start..start + image_size,
+			"loader",
+			Reservation::Reclaimable,
+		)
+	};
+
+	let framebuffer = gop::framebuffer(config.resolution);
+	if let Some(framebuffer) = framebuffer
+		&& framebuffer::console::enabled()
+	{
+		CONSOLE.lock().set_framebuffer(framebuffer);
+	}
+
+	let fdt = || {
+		let mut fdt = Fdt::new("uefi")
+			.unwrap()
+			.reserved_memory(loader.clone())
+			.unwrap();
+
+		#[cfg(not(target_arch = "x86_64"))]
+		if let Some(dtb) = &dtb {
+			fdt = fdt.merge(dtb).unwrap();
+		}
+
+		if let Some(rsdp) = rsdp {
+			fdt = fdt
+				.rsdp(u64::try_from(rsdp.expose_provenance()).unwrap())
+				.unwrap();
+		}
+
+		if let Some(cc_blob) = cc_blob {
+			fdt = fdt.efi_sev_snp_cc_blob(cc_blob).unwrap()
+		};
+
+		if let Some(bootargs) = &bootargs {
+			fdt = fdt.bootargs(bootargs.clone()).unwrap();
+		}
+
+		fdt = fdt.app_sha256(app_sha256).unwrap();
+
+		if let Some(framebuffer) = framebuffer {
+			fdt = fdt.framebuffer(framebuffer).unwrap();
+		}
+
+		for (name, module) in &modules {
+			let start = u64::try_from(module.as_ptr().expose_provenance()).unwrap();
+			let end = start + u64::try_from(module.len()).unwrap();
+			fdt = fdt.module(start..end, name).unwrap();
+		}
 
-	if let Some(cc_blob) = detect_cc_blob() {
-		fdt = fdt.efi_sev_snp_cc_blob(cc_blob).unwrap()
+		fdt
 	};
 
-	if let Some(kernel_args) = kernel_args {
-		fdt = fdt.bootargs(kernel_args.hermit_args).unwrap();
-	} else if let Some(bootargs) = esp.read_bootargs() {
-		fdt = fdt.bootargs(bootargs).unwrap();
+	if let Some(mut tpm) = tpm {
+		for (name, module) in &modules {
+			tpm.measure(tpm::PCR_FILES, name, module);
+		}
+
+		if let Some(bootargs) = &bootargs {
+			tpm.measure(tpm::PCR_CONFIG, "Hermit bootargs", bootargs.as_bytes());
+		}
+
+		// The memory map is only final after exiting boot services and is thus not measured.
+		// The rng-seed is secret and thus not measured either.
+		// The memory reservation block includes the address of the device tree itself and is thus not measured either.
+		let fdt = fdt().finish_unreserved().unwrap();
+		tpm.measure(tpm::PCR_CONFIG, "Hermit device tree", &fdt);
 	}
 
+	// The EFI RNG protocol is not available after exiting boot services.
+	let rng_seed = random::seed();
+
+	#[cfg(target_arch = "riscv64")]
+	let hart_id = riscv::boot_hart_id();
+
 	allocator::exit_boot_services();
 	let mut memory_map = unsafe { boot::exit_boot_services(None) };
 
+	let mut fdt = fdt();
+	if let Some(rng_seed) = &rng_seed {
+		fdt = fdt.rng_seed(rng_seed).unwrap();
+	}
 	let fdt = fdt.memory_map(&mut memory_map).unwrap().finish().unwrap();
 
-	unsafe { boot_kernel(kernel_info, fdt) }
+	#[cfg(target_arch = "x86_64")]
+	let default_serial_port = SerialPortBase::new(arch::SERIAL_IO_PORT);
+	#[cfg(target_arch = "aarch64")]
+	let default_serial_port = SerialPortBase::new(arch::serial_port_address());
+	#[cfg(target_arch = "riscv64")]
+	let default_serial_port = None;
+	let serial_port_base = config.serial_port.or(default_serial_port);
+
+	// The kernel is entered with the loader's page tables, which identity-map the RAM.
+	#[cfg(target_arch = "aarch64")]
+	let serial_port_base = {
+		let ram = memory_map
+			.entries()
+			.filter(|desc| {
+				matches!(
+					desc.ty,
+					MemoryType::LOADER_CODE
+						| MemoryType::LOADER_DATA
+						| MemoryType::BOOT_SERVICES_CODE
+						| MemoryType::BOOT_SERVICES_DATA
+						| MemoryType::CONVENTIONAL
+						| MemoryType::ACPI_RECLAIM
+				)
+			})
+			.map(|desc| desc.phys_start..desc.phys_start + desc.page_count * PAGE_SIZE as u64);
+		unsafe { arch::init_page_tables(ram, serial_port_base) }
+	};
+
+	unsafe {
+		boot_kernel(
+			kernel_info,
+			fdt,
+			serial_port_base,
+			#[cfg(target_arch = "riscv64")]
+			hart_id,
+		)
+	}
 }
 
-pub unsafe fn boot_kernel(kernel_info: LoadedKernel, fdt: Vec<u8>) -> ! {
+pub unsafe fn boot_kernel(
+	kernel_info: LoadedKernel,
+	fdt: Vec<u8>,
+	serial_port_base: Option<SerialPortBase>,
+	#[cfg(target_arch = "riscv64")] hart_id: usize,
+) -> ! {
 	let LoadedKernel {
 		load_info,
 		entry_point,
@@ -89,7 +284,7 @@ pub unsafe fn boot_kernel(kernel_info: LoadedKernel, fdt: Vec<u8>) -> ! {
 	let boot_info = BootInfo {
 		hardware_info: HardwareInfo {
 			phys_addr_range: 0..0,
-			serial_port_base: SerialPortBase::new(arch::SERIAL_IO_PORT),
+			serial_port_base,
 			device_tree,
 		},
 		load_info,
@@ -103,7 +298,25 @@ pub unsafe fn boot_kernel(kernel_info: LoadedKernel, fdt: Vec<u8>) -> ! {
 	let stack = ptr::with_exposed_provenance_mut(stack);
 	let raw_boot_info = boot_info.write();
 
-	unsafe { arch::enter_kernel(stack, entry, raw_boot_info) }
+	#[cfg(not(target_arch = "riscv64"))]
+	unsafe {
+		arch::enter_kernel(stack, entry, raw_boot_info)
+	}
+	#[cfg(target_arch = "riscv64")]
+	unsafe {
+		arch::enter_kernel(stack, entry, hart_id, raw_boot_info)
+	}
+}
+
+fn decompress(compression: Compression, image: &[u8]) -> Vec<u8> {
+	let size = compression
+		.decompressed_size(image)
+		.unwrap_or_else(|err| panic!("could not decompress the Hermit application: {err}"));
+	let mut kernel_image = vec![0; size];
+	compression
+		.decompress_into(image, &mut kernel_image)
+		.unwrap_or_else(|err| panic!("could not decompress the Hermit application: {err}"));
+	kernel_image
 }
 
 fn alloc_page_slice(size: usize) -> uefi::Result<&'static mut [MaybeUninit<u8>]> {
@@ -116,26 +329,104 @@ fn alloc_page_slice(size: usize) -> uefi::Result<&'static mut [MaybeUninit<u8>]>
 	Ok(unsafe { slice::from_raw_parts_mut(ptr.cast().as_ptr(), size) })
 }
 
-/// Returns the RSDP.
+/// Allocates pages at a random [`kaslr::ALIGN`]-aligned address in conventional memory.
+fn alloc_random_page_slice(size: usize) -> uefi::Result<&'static mut [MaybeUninit<u8>]> {
+	let size = size.align_up(PAGE_SIZE);
+
+	// The aligned start addresses that leave room for `size` bytes in each free region
+	let starts = boot::memory_map(MemoryType::LOADER_DATA)?
+		.entries()
+		.filter(|desc| desc.ty == MemoryType::CONVENTIONAL)
+		.filter_map(|desc| {
+			// Never place the kernel at address 0.
+			let start = desc.phys_start.max(1).align_up(kaslr::ALIGN);
+			let end = desc.phys_start + desc.page_count * PAGE_SIZE as u64;
+			let last = end.checked_sub(size as u64)?.align_down(kaslr::ALIGN);
+			(start <= last).then_some(start..last + kaslr::ALIGN)
+		})
+		.collect::<Vec<_>>();
+
+	let free = starts
+		.iter()
+		.map(|starts| starts.end - starts.start)
+		.sum::<u64>();
+	let mut offset = kaslr::random_offset(free.saturating_sub(kaslr::ALIGN));
+	let addr = starts
+		.into_iter()
+		.find_map(|starts| {
+			let addr = starts.start + offset;
+			offset = offset.saturating_sub(starts.end - starts.start);
+			(addr < starts.end).then_some(addr)
+		})
+		.ok_or(Status::OUT_OF_RESOURCES)?;
+	info!("KASLR: Loading kernel at {addr:#x}");
+
+	let ptr = boot::allocate_pages(
+		AllocateType::Address(addr),
+		MemoryType::LOADER_DATA,
+		size / PAGE_SIZE,
+	)?;
+	Ok(unsafe { slice::from_raw_parts_mut(ptr.cast().as_ptr(), size) })
+}
+
+/// Fills `buf` with random bytes from the EFI RNG protocol.
+///
+/// Returns `false` if the protocol is not available.
+/// This must be called before exiting boot services.
+pub fn fill_random_bytes(buf: &mut [u8]) -> bool {
+	let Ok(handle) = boot::get_handle_for_protocol::<Rng>() else {
+		return false;
+	};
+
+	let result =
+		boot::open_protocol_exclusive::<Rng>(handle).and_then(|mut rng| rng.get_rng(None, buf));
+	match result {
+		Ok(()) => true,
+		Err(err) => {
+			error!("Could not get random bytes from the EFI RNG protocol: {err}");
+			false
+		}
+	}
+}
+
+/// Returns the RSDP, if the firmware provides ACPI tables.
 ///
 /// This must be called before exiting boot services.
 /// See [5.2.5.2. Finding the RSDP on UEFI Enabled Systems — ACPI Specification 6.5 documentation](https://uefi.org/specs/ACPI/6.5/05_ACPI_Software_Programming_Model.html#finding-the-rsdp-on-uefi-enabled-systems) for details.
-fn rsdp() -> *const c_void {
+fn rsdp() -> Option<*const c_void> {
 	system::with_config_table(|config_table| {
 		let (rsdp, version) = if let Some(entry) = config_table
 			.iter()
 			.find(|entry| entry.guid == ConfigTableEntry::ACPI2_GUID)
 		{
 			(entry.address, 2)
-		} else {
-			let entry = config_table
-				.iter()
-				.find(|entry| entry.guid == ConfigTableEntry::ACPI_GUID)
-				.unwrap();
+		} else if let Some(entry) = config_table
+			.iter()
+			.find(|entry| entry.guid == ConfigTableEntry::ACPI_GUID)
+		{
 			(entry.address, 1)
+		} else {
+			info!("ACPI RSDP not found");
+			return None;
 		};
 		info!("Found ACPI {version} RSDP at {rsdp:p}");
-		rsdp
+		Some(rsdp)
+	})
+}
+
+/// Returns the firmware's device tree from the EFI configuration table, if present.
+///
+/// This does not log, since the console uses it to find its serial port.
+/// See [4.6.1.3. Devicetree Tables — UEFI Specification 2.10 documentation](https://uefi.org/specs/UEFI/2.10/04_EFI_System_Table.html#devicetree-tables).
+#[cfg(not(target_arch = "x86_64"))]
+pub fn device_tree() -> Option<&'static [u8]> {
+	/// EFI Device Tree configuration table.
+	const DTB_GUID: Guid = guid!("b1b621d5-f19c-41a5-830b-d9152c69aae0");
+
+	system::with_config_table(|config_table| {
+		let entry = config_table.iter().find(|entry| entry.guid == DTB_GUID)?;
+		let fdt = unsafe { fdt::Fdt::from_ptr(entry.address.cast()) }.ok()?;
+		Some(unsafe { slice::from_raw_parts(entry.address.cast(), fdt.total_size()) })
 	})
 }
 
@@ -162,6 +453,131 @@ impl BootPartition {
 			.or_else(|| self.read_bootargs_at(cstr16!(r"\EFI\BOOT\hermit-bootargs")))
 	}
 
+	pub fn read_signature(&mut self) -> Option<Vec<u8>> {
+		self.read_signature_at(cstr16!(r"\EFI\hermit\hermit-app.sig"))
+			.or_else(|| self.read_signature_at(cstr16!(r"\EFI\BOOT\hermit-app.sig")))
+	}
+
+	/// Lets the user select one of the Hermit applications in `\EFI\hermit`.
+	///
+	/// Files ending in `.sig` or `.bootargs`, `hermit-bootargs`, and `loader.conf` are not considered applications.
+	/// `hermit-app` is the default application.
+	/// Returns `None` if there are no applications in `\EFI\hermit`.
+	pub fn select_app(&mut self, timeout: Duration) -> Option<CString16> {
+		let dir = cstr16!(r"\EFI\hermit");
+		let mut apps = match self.fs.read_dir(dir) {
+			Ok(entries) => entries
+				.filter_map(Result::ok)
+				.filter(|info| !info.is_directory())
+				.map(|info| String::from(info.file_name()))
+				.filter(|name| {
+					!name.ends_with(".sig")
+						&& !name.ends_with(".bootargs")
+						&& name != "hermit-bootargs"
+						&& name != "loader.conf"
+				})
+				.collect::<Vec<_>>(),
+			Err(fs::Error::Io(err)) if err.uefi_error.status() == Status::NOT_FOUND => {
+				info!("Hermit applications not found at {dir}");
+				return None;
+			}
+			Err(err) => {
+				let err = anyhow!(err);
+				error!("Could not read Hermit applications: {err:#}");
+				return None;
+			}
+		};
+		apps.sort_unstable();
+
+		let default = apps
+			.iter()
+			.position(|app| app == "hermit-app")
+			.unwrap_or_default();
+		let app = match apps.len() {
+			0 => return None,
+			1 => &apps[0],
+			_ => &apps[menu::select(&apps, default, timeout)],
+		};
+
+		Some(CString16::try_from(format!(r"{dir}\{app}").as_str()).unwrap())
+	}
+
+	/// Reads all additional modules from `\EFI\hermit\modules`.
+	///
+	/// The modules are leaked and stay in memory after exiting boot services.
+	pub fn read_modules(&mut self) -> Vec<(String, &'static [u8])> {
+		let dir = cstr16!(r"\EFI\hermit\modules");
+		let names = match self.fs.read_dir(dir) {
+			Ok(entries) => entries
+				.filter_map(Result::ok)
+				.filter(|info| !info.is_directory())
+				.map(|info| String::from(info.file_name()))
+				.collect::<Vec<_>>(),
+			Err(fs::Error::Io(err)) if err.uefi_error.status() == Status::NOT_FOUND => {
+				info!("Hermit modules not found at {dir}");
+				return Vec::new();
+			}
+			Err(err) => {
+				let err = anyhow!(err);
+				error!("Could not read Hermit modules: {err:#}");
+				return Vec::new();
+			}
+		};
+
+		names
+			.into_iter()
+			.filter_map(|name| {
+				let path = CString16::try_from(format!(r"{dir}\{name}").as_str()).unwrap();
+				self.read_module_at(&path)
+			})
+			.collect()
+	}
+
+	/// Reads the loader configuration from `loader.conf`.
+	///
+	/// Returns the default configuration if there is no configuration file.
+	pub fn read_config(&mut self) -> Config {
+		self.read_config_at(cstr16!(r"\EFI\hermit\loader.conf"))
+			.or_else(|| self.read_config_at(cstr16!(r"\EFI\BOOT\loader.conf")))
+			.unwrap_or_default()
+	}
+
+	/// Returns whether the Hermit application at `path` exists.
+	fn app_exists(&mut self, path: &CStr16) -> bool {
+		match self.fs.try_exists(path) {
+			Ok(true) => true,
+			Ok(false) => {
+				error!("Hermit application not found at {path}");
+				false
+			}
+			Err(err) => {
+				let err = anyhow!(err);
+				error!("Could not find Hermit application: {err:#}");
+				false
+			}
+		}
+	}
+
+	/// Reads the module at `path` with its file name as module string.
+	///
+	/// The module is leaked and stays in memory after exiting boot services.
+	fn read_module_at(&mut self, path: &CStr16) -> Option<(String, &'static [u8])> {
+		let name = String::from(path);
+		let name = name.rsplit('\\').next().unwrap().into();
+		match self.fs.read(path) {
+			Ok(data) => {
+				let len = data.len();
+				info!("Read Hermit module from {path} (size = {len} B)");
+				Some((name, &*data.leak()))
+			}
+			Err(err) => {
+				let err = anyhow!(err);
+				error!("Could not read Hermit module: {err:#}");
+				None
+			}
+		}
+	}
+
 	fn read_app_at<P: AsRef<Path>>(&mut self, path: P) -> Option<Vec<u8>> {
 		fn inner(fs: &mut FileSystem, path: &Path) -> Option<Vec<u8>> {
 			match fs.read(path) {
@@ -181,6 +597,50 @@ impl BootPartition {
 		inner(&mut self.fs, path.as_ref())
 	}
 
+	fn read_signature_at<P: AsRef<Path>>(&mut self, path: P) -> Option<Vec<u8>> {
+		fn inner(fs: &mut FileSystem, path: &Path) -> Option<Vec<u8>> {
+			match fs.read(path) {
+				Ok(signature) => {
+					info!("Read Hermit application signature from {path}");
+					Some(signature)
+				}
+				Err(fs::Error::Io(err)) if err.uefi_error.status() == Status::NOT_FOUND => {
+					info!("Hermit application signature not found at {path}");
+					None
+				}
+				Err(err) => {
+					let err = anyhow!(err);
+					error!("Could not read Hermit application signature: {err:#}");
+					None
+				}
+			}
+		}
+
+		inner(&mut self.fs, path.as_ref())
+	}
+
+	fn read_config_at<P: AsRef<Path>>(&mut self, path: P) -> Option<Config> {
+		fn inner(fs: &mut FileSystem, path: &Path) -> Option<Config> {
+			match fs.read_to_string(path) {
+				Ok(config) => {
+					info!("Read loader configuration from {path}");
+					Some(Config::parse(&String::from(path.to_cstr16()), &config))
+				}
+				Err(fs::Error::Io(err)) if err.uefi_error.status() == Status::NOT_FOUND => {
+					info!("Loader configuration not found at {path}");
+					None
+				}
+				Err(err) => {
+					let err = anyhow!(err);
+					error!("Could not read loader configuration: {err:#}");
+					None
+				}
+			}
+		}
+
+		inner(&mut self.fs, path.as_ref())
+	}
+
 	fn read_bootargs_at<P: AsRef<Path>>(&mut self, path: P) -> Option<String> {
 		fn inner(fs: &mut FileSystem, path: &Path) -> Option<String> {
 			match fs.read_to_string(path) {
@@ -204,6 +664,13 @@ impl BootPartition {
 	}
 }
 
+/// Returns `path` with `suffix` appended.
+fn with_suffix(path: &CString16, suffix: &str) -> CString16 {
+	let mut path = String::from(path);
+	path.push_str(suffix);
+	CString16::try_from(path.as_str()).unwrap()
+}
+
 /// Try to locate an AMD SEV-SNP confidential computing blob, and returns its address if found.
 /// This function is only relevant on AMD SEV-SNP guests, and will do mostly nothing otherwise.
 fn detect_cc_blob() -> Option<u64> {
@@ -0,0 +1,53 @@
+//! Boot hart discovery through the RISC-V EFI boot protocol.
+//!
+//! See [RISC-V UEFI Protocol Specification](https://github.com/riscv-non-isa/riscv-uefi).
+
+use log::{info, warn};
+use uefi::proto::unsafe_protocol;
+use uefi::{Status, boot};
+
+/// `RISCV_EFI_BOOT_PROTOCOL`
+#[repr(C)]
+#[unsafe_protocol("ccd15fec-6f73-4eec-8395-3e69e4b940bf")]
+struct RiscvBoot {
+	revision: u64,
+	get_boot_hart_id:
+		unsafe extern "efiapi" fn(this: *mut Self, boot_hart_id: *mut usize) -> Status,
+}
+
+/// Returns the ID of the hart that the firmware started us on.
+///
+/// Firmware without the RISC-V EFI boot protocol provides `/chosen/boot-hartid` in the device tree instead.
+/// This must be called before exiting boot services.
+pub fn boot_hart_id() -> usize {
+	let hart_id = protocol_boot_hart_id()
+		.or_else(device_tree_boot_hart_id)
+		.expect("Could not determine the boot hart ID");
+	info!("Boot hart ID: {hart_id}");
+	hart_id
+}
+
+fn protocol_boot_hart_id() -> Option<usize> {
+	let Ok(handle) = boot::get_handle_for_protocol::<RiscvBoot>() else {
+		info!("RISC-V EFI boot protocol not found");
+		return None;
+	};
+
+	let mut protocol = boot::open_protocol_exclusive::<RiscvBoot>(handle).ok()?;
+	let get_boot_hart_id = protocol.get_boot_hart_id;
+	let mut hart_id = 0;
+	let status = unsafe { get_boot_hart_id(&mut *protocol, &mut hart_id) };
+	if !status.is_success() {
+		warn!("Could not get the boot hart ID: {status:?}");
+		return None;
+	}
+
+	Some(hart_id)
+}
+
+fn device_tree_boot_hart_id() -> Option<usize> {
+	let fdt = fdt::Fdt::new(super::device_tree()?).ok()?;
+	fdt.find_node("/chosen")?
+		.property("boot-hartid")?
+		.as_usize()
+}
@@ -0,0 +1,72 @@
+//! Measured boot through the EFI TCG2 protocol.
+//!
+//! Following GRUB's PCR usage, the Hermit application is measured into PCR 9,
+//! while the bootargs and the device tree are measured into PCR 8.
+//! The device tree is measured as built before exiting boot services,
+//! that is without the memory map, `/chosen/rng-seed`, and the memory reservation block.
+//! See [TCG PC Client Platform Firmware Profile Specification](https://trustedcomputinggroup.org/resource/pc-client-specific-platform-firmware-profile-specification/).
+
+use log::{error, info};
+use uefi::boot::{self, ScopedProtocol};
+use uefi::proto::tcg::v2::{HashLogExtendEventFlags, PcrEventInputs, Tcg};
+use uefi::proto::tcg::{EventType, PcrIndex};
+
+/// PCR for loaded files.
+pub const PCR_FILES: PcrIndex = PcrIndex(9);
+
+/// PCR for configuration such as the command line.
+pub const PCR_CONFIG: PcrIndex = PcrIndex(8);
+
+pub struct Tpm {
+	tcg: ScopedProtocol<Tcg>,
+}
+
+impl Tpm {
+	/// Opens the TCG2 protocol, if a TPM is present.
+	pub fn open() -> Option<Self> {
+		let Ok(handle) = boot::get_handle_for_protocol::<Tcg>() else {
+			info!("No TPM found, booting without measurements");
+			return None;
+		};
+
+		let mut tcg = match boot::open_protocol_exclusive::<Tcg>(handle) {
+			Ok(tcg) => tcg,
+			Err(err) => {
+				error!("Could not open the TCG2 protocol: {err}");
+				return None;
+			}
+		};
+
+		match tcg.get_capability() {
+			Ok(capability) if capability.tpm_present() => {
+				info!(
+					"Found TPM (protocol version {:?}, active PCR banks: {:?})",
+					capability.protocol_version, capability.active_pcr_banks
+				);
+				Some(Self { tcg })
+			}
+			Ok(_) => {
+				info!("No TPM present, booting without measurements");
+				None
+			}
+			Err(err) => {
+				error!("Could not query the TPM capabilities: {err}");
+				None
+			}
+		}
+	}
+
+	/// Extends `pcr` with the digest of `data` and logs an `EV_IPL` event with `description`.
+	pub fn measure(&mut self, pcr: PcrIndex, description: &str, data: &[u8]) {
+		let event =
+			PcrEventInputs::new_in_box(pcr, EventType::IPL, description.as_bytes()).unwrap();
+
+		match self
+			.tcg
+			.hash_log_extend_event(HashLogExtendEventFlags::empty(), data, &event)
+		{
+			Ok(()) => info!("Measured {description} into PCR {}", pcr.0),
+			Err(err) => error!("Could not measure {description}: {err}"),
+		}
+	}
+}
@@ -0,0 +1,101 @@
+//! Entropy for randomizing the kernel.
+//!
+//! Random numbers come from the firmware (UEFI RNG protocol) if available,
+//! then from the CPU (`RDSEED`/`RDRAND` on x86-64, `RNDR` on AArch64),
+//! and finally from the architecture's cycle counter.
+//! The latter is not suitable for cryptographic purposes.
+
+use core::fmt;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use log::{info, warn};
+
+use crate::arch;
+
+/// The source of random numbers.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Source {
+	#[cfg_attr(not(target_os = "uefi"), expect(unused))]
+	Firmware,
+	Cpu,
+	Timer,
+}
+
+impl fmt::Display for Source {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let s = match self {
+			Self::Firmware => "firmware",
+			Self::Cpu => "CPU",
+			Self::Timer => "timer",
+		};
+		f.write_str(s)
+	}
+}
+
+/// The size of seeds for the kernel's random number generator.
+pub const SEED_SIZE: usize = 64;
+
+/// Returns a seed for the kernel's random number generator.
+///
+/// Returns `None` if there is no firmware or CPU entropy source, since the timer is not suitable for seeding.
+pub fn seed() -> Option<[u8; SEED_SIZE]> {
+	let mut seed = [0; SEED_SIZE];
+	match fill_bytes(&mut seed) {
+		Source::Timer => {
+			warn!("No entropy source found, booting without rng-seed");
+			None
+		}
+		source => {
+			info!("Generated rng-seed with entropy from {source}");
+			Some(seed)
+		}
+	}
+}
+
+/// Returns a random `u64` and its source.
+pub fn random_u64() -> (u64, Source) {
+	let mut bytes = [0; 8];
+	let source = fill_bytes(&mut bytes);
+	(u64::from_ne_bytes(bytes), source)
+}
+
+/// Fills `buf` with random bytes and returns their source.
+pub fn fill_bytes(buf: &mut [u8]) -> Source {
+	#[cfg(target_os = "uefi")]
+	if crate::os::fill_random_bytes(buf) {
+		return Source::Firmware;
+	}
+
+	if fill_with(buf, arch::random_u64) {
+		return Source::Cpu;
+	}
+
+	fill_with(buf, || Some(timer_u64()));
+	Source::Timer
+}
+
+fn fill_with(buf: &mut [u8], mut f: impl FnMut() -> Option<u64>) -> bool {
+	for chunk in buf.chunks_mut(8) {
+		let Some(value) = f() else {
+			return false;
+		};
+		chunk.copy_from_slice(&value.to_ne_bytes()[..chunk.len()]);
+	}
+	true
+}
+
+/// Returns a pseudo-random `u64` derived from the cycle counter using [SplitMix64].
+///
+/// [SplitMix64]: https://prng.di.unimi.it/splitmix64.c
+fn timer_u64() -> u64 {
+	static STATE: AtomicU64 = AtomicU64::new(0);
+
+	// We cannot use read-modify-write atomics before the MMU is enabled on AArch64.
+	let state = (STATE.load(Ordering::Relaxed) ^ arch::ticks()).wrapping_add(0x9e37_79b9_7f4a_7c15);
+	STATE.store(state, Ordering::Relaxed);
+
+	let mut z = state;
+	z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+	z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+	z ^ (z >> 31)
+}
@@ -0,0 +1,56 @@
+//! Memory regions that still hold live data when entering the kernel.
+//!
+//! Each platform registers these regions in the device tree, so that the kernel does not allocate them before it is done with them.
+
+use core::ops::Range;
+
+/// How long a reserved memory region must be preserved.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Reservation {
+	/// The region may be reused by the kernel after booting.
+	///
+	/// Such regions are published as `reusable` in `/reserved-memory`.
+	Reclaimable,
+
+	/// The region must never be used as RAM.
+	///
+	/// Such regions are published as `no-map` in `/reserved-memory` and in the memory reservation block.
+	Permanent,
+}
+
+/// A reserved memory region.
+#[derive(Clone, Debug)]
+pub struct ReservedMemory {
+	pub memory: Range<u64>,
+
+	/// The name of the region, which is published as `/reserved-memory/<name>@<ADDRESS>` with the compatible string `hermit,<name>`.
+	pub name: &'static str,
+
+	pub reservation: Reservation,
+}
+
+impl ReservedMemory {
+	pub fn new(memory: Range<u64>, name: &'static str, reservation: Reservation) -> Self {
+		Self {
+			memory,
+			name,
+			reservation,
+		}
+	}
+
+	/// Returns the reclaimable region of `bytes`.
+	#[cfg_attr(target_os = "uefi", expect(unused))]
+	pub fn reclaimable(bytes: &[u8], name: &'static str) -> Self {
+		let range = bytes.as_ptr_range();
+		let memory = range.start.expose_provenance() as u64..range.end.expose_provenance() as u64;
+		Self::new(memory, name, Reservation::Reclaimable)
+	}
+
+	/// Returns the reclaimable region of the loader image, which includes the boot info.
+	#[cfg(target_os = "none")]
+	pub fn loader() -> Self {
+		let start = elf_symbols::executable_start().expose_provenance() as u64;
+		let end = elf_symbols::executable_end().expose_provenance() as u64;
+		Self::new(start..end, "loader", Reservation::Reclaimable)
+	}
+}
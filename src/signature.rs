@@ -0,0 +1,132 @@
+//! Ed25519 signature verification of Hermit applications.
+//!
+//! The public key is embedded at build time through the `LOADER_PUBLIC_KEY` environment variable
+//! as 64 hexadecimal digits.
+//! If `LOADER_REQUIRE_SIGNATURE` is set to a non-empty value at build time,
+//! the loader refuses to boot applications without a valid signature.
+//! Otherwise, unsigned applications are booted with a warning.
+//!
+//! Signatures are either detached or appended to the application as
+//! `<APP> || <SIGNATURE (64 B)> || "~hermit-ed25519~"`.
+
+use ed25519_compact::{PublicKey, Signature};
+use log::{error, info, warn};
+
+use crate::hex;
+
+/// Marks an Ed25519 signature appended to the application image.
+const APPENDED_MAGIC: &[u8; 16] = b"~hermit-ed25519~";
+
+/// The public key from `LOADER_PUBLIC_KEY`, which is validated at compile time.
+const PUBLIC_KEY: Option<[u8; PublicKey::BYTES]> = match option_env!("LOADER_PUBLIC_KEY") {
+	Some(hex) => {
+		let mut key = [0; PublicKey::BYTES];
+		if hex::decode(hex, &mut key).is_none() {
+			panic!("LOADER_PUBLIC_KEY is not a hexadecimal Ed25519 public key");
+		}
+		Some(key)
+	}
+	None => None,
+};
+
+fn public_key() -> Option<PublicKey> {
+	PUBLIC_KEY.map(PublicKey::new)
+}
+
+fn require_signature() -> bool {
+	option_env!("LOADER_REQUIRE_SIGNATURE").is_some_and(|val| !val.is_empty())
+}
+
+/// Splits an appended signature off `image`.
+fn split_appended(image: &[u8]) -> (&[u8], Option<&[u8]>) {
+	let Some(rest) = image.strip_suffix(APPENDED_MAGIC) else {
+		return (image, None);
+	};
+
+	match rest
+		.len()
+		.checked_sub(Signature::BYTES)
+		.and_then(|mid| rest.split_at_checked(mid))
+	{
+		Some((image, signature)) => (image, Some(signature)),
+		None => (image, None),
+	}
+}
+
+/// Verifies the signature of `image` and returns the image without any appended signature.
+///
+/// `detached` takes precedence over an appended signature.
+///
+/// # Panics
+///
+/// Panics if the signature is invalid or if a signature is required but missing.
+pub fn verify<'a>(image: &'a [u8], detached: Option<&[u8]>) -> &'a [u8] {
+	let (image, appended) = split_appended(image);
+
+	let Some(signature) = detached.or(appended) else {
+		if require_signature() {
+			error!("The Hermit application is not signed.");
+			panic!("refusing to boot unsigned Hermit application");
+		}
+		warn!("The Hermit application is not signed.");
+		return image;
+	};
+
+	let kind = if detached.is_some() {
+		"detached"
+	} else {
+		"appended"
+	};
+
+	let Some(public_key) = public_key() else {
+		if require_signature() {
+			error!("The loader was built without LOADER_PUBLIC_KEY.");
+			panic!("cannot verify the signature of the Hermit application");
+		}
+		warn!("Found {kind} signature, but the loader was built without LOADER_PUBLIC_KEY.");
+		return image;
+	};
+
+	let result =
+		Signature::from_slice(signature).and_then(|signature| public_key.verify(image, &signature));
+	if let Err(err) = result {
+		error!("Invalid {kind} signature of the Hermit application: {err}");
+		panic!("refusing to boot Hermit application with invalid signature");
+	}
+
+	info!("Verified {kind} Ed25519 signature of the Hermit application");
+	image
+}
+
+#[cfg(all(test, not(target_os = "none")))]
+mod tests {
+	use alloc::vec::Vec;
+
+	use super::*;
+
+	#[test]
+	fn split_appended_without_magic() {
+		let image = b"\x7fELF application";
+		assert_eq!(split_appended(image), (&image[..], None));
+	}
+
+	#[test]
+	fn split_appended_short() {
+		let image: Vec<u8> = [&[0; Signature::BYTES - 1][..], APPENDED_MAGIC].concat();
+		assert_eq!(split_appended(&image), (&image[..], None));
+	}
+
+	#[test]
+	fn split_appended_valid() {
+		let signature = [0x5a; Signature::BYTES];
+		let image: Vec<u8> = [&b"\x7fELF application"[..], &signature, APPENDED_MAGIC].concat();
+		assert_eq!(
+			split_appended(&image),
+			(&b"\x7fELF application"[..], Some(&signature[..]))
+		);
+
+		// Only the signature
+		let image: Vec<u8> = [&signature[..], APPENDED_MAGIC].concat();
+		assert_eq!(split_appended(&image), (&[][..], Some(&signature[..])));
+	}
+}
@@ -0,0 +1,47 @@
+//! Coarse time measurements based on the architecture's cycle counter.
+
+use core::fmt;
+use core::time::Duration;
+
+use crate::arch;
+
+/// A point in time, measured in architecture-specific ticks.
+#[derive(Clone, Copy, Debug)]
+pub struct Instant {
+	ticks: u64,
+}
+
+impl Instant {
+	pub fn now() -> Self {
+		Self {
+			ticks: arch::ticks(),
+		}
+	}
+
+	pub fn elapsed(&self) -> Elapsed {
+		Elapsed {
+			ticks: arch::ticks().wrapping_sub(self.ticks),
+		}
+	}
+}
+
+/// The time elapsed since an [`Instant`].
+///
+/// This is displayed as a [`Duration`] if the tick frequency is known and as raw ticks otherwise.
+#[derive(Clone, Copy, Debug)]
+pub struct Elapsed {
+	ticks: u64,
+}
+
+impl fmt::Display for Elapsed {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match arch::tick_frequency() {
+			Some(frequency) => {
+				let nanos = u128::from(self.ticks) * 1_000_000_000 / u128::from(frequency);
+				let duration = Duration::from_nanos(nanos.try_into().unwrap());
+				write!(f, "{duration:?}")
+			}
+			None => write!(f, "{} ticks", self.ticks),
+		}
+	}
+}
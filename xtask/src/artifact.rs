@@ -68,6 +68,19 @@ impl Artifact {
 		.into()
 	}
 
+	pub fn dist_binary(&self) -> Option<Object> {
+		let binary_dist_name = self.target.binary_dist_name()?;
+		let binary = [
+			self.target_dir(),
+			self.profile_path_component().as_ref(),
+			binary_dist_name.as_ref(),
+		]
+		.iter()
+		.collect::<PathBuf>()
+		.into();
+		Some(binary)
+	}
+
 	#[cfg(feature = "ci")]
 	pub fn ci_image(&self, image: &str) -> PathBuf {
 		["data", self.target.arch(), image].iter().collect()
@@ -48,6 +48,15 @@ impl Build {
 		}
 
 		eprintln!("Loader available at {}", dist_object.as_ref().display());
+
+		if let Some(dist_binary) = self.artifact.dist_binary() {
+			eprintln!("Converting object to raw binary");
+			dist_object.convert_to_binary(&dist_binary)?;
+			eprintln!(
+				"Loader binary available at {}",
+				dist_binary.as_ref().display()
+			);
+		}
 		Ok(())
 	}
 
@@ -73,6 +82,11 @@ impl Build {
 		self.artifact.dist_object().into()
 	}
 
+	#[cfg(feature = "ci")]
+	pub fn dist_binary(&self) -> Option<PathBuf> {
+		self.artifact.dist_binary().map(Into::into)
+	}
+
 	#[cfg(feature = "ci")]
 	pub fn target(&self) -> Target {
 		self.artifact.target
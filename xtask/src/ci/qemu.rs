@@ -1,7 +1,8 @@
+use std::path::PathBuf;
 use std::process::{Command, ExitStatus};
 use std::{env, fs};
 
-use anyhow::{Result, ensure};
+use anyhow::{Result, bail, ensure};
 use clap::Args;
 use sysinfo::{CpuRefreshKind, System};
 use xshell::cmd;
@@ -29,10 +30,15 @@ pub struct Qemu {
 	u_boot: bool,
 
 	/// Run with an EFI System Partition.
-	/// Only used for x86_64-uefi
+	/// Only used for UEFI targets
 	#[arg(long)]
 	esp: bool,
 
+	/// Attach a software TPM using swtpm.
+	/// Only used for x86_64-uefi
+	#[arg(long)]
+	tpm: bool,
+
 	#[command(flatten)]
 	build: Build,
 
@@ -62,6 +68,10 @@ impl Qemu {
 
 		self.prepare_image()?;
 
+		if self.tpm {
+			self.start_swtpm()?;
+		}
+
 		let sh = crate::sh()?;
 		let target = self.build.target();
 		let qemu = target.qemu();
@@ -74,7 +84,8 @@ impl Qemu {
 			.args(&["-serial", "stdio"])
 			.args(self.machine_args())
 			.args(self.cpu_args())
-			.args(self.memory_args());
+			.args(self.memory_args())
+			.args(self.tpm_args());
 
 		eprintln!("$ {qemu}");
 		let status = Command::from(qemu).status()?;
@@ -87,13 +98,23 @@ impl Qemu {
 		let sh = crate::sh()?;
 
 		match self.build.target() {
-			Target::X86_64Uefi if self.esp => {
+			target @ (Target::X86_64Uefi | Target::Aarch64Uefi | Target::Riscv64Uefi)
+				if self.esp =>
+			{
 				sh.remove_path("target/esp")?;
 
 				// Spec: https://uefi.org/specs/UEFI/2.11/03_Boot_Manager.html#removable-media-boot-behavior
 				// EDK II: https://github.com/tianocore/edk2/blob/edk2-stable202511/MdePkg/Include/Uefi/UefiSpec.h#L2264-L2273
+				let boot_file = match target {
+					Target::X86_64Uefi => "BOOTX64.EFI",
+					Target::Aarch64Uefi => "BOOTAA64.EFI",
+					_ => "BOOTRISCV64.EFI",
+				};
 				sh.create_dir("target/esp/EFI/BOOT")?;
-				sh.copy_file(self.build.dist_object(), "target/esp/EFI/BOOT/BOOTX64.EFI")?;
+				sh.copy_file(
+					self.efi_application(),
+					format!("target/esp/EFI/BOOT/{boot_file}"),
+				)?;
 
 				sh.create_dir("target/esp/EFI/hermit")?;
 				sh.copy_file(
@@ -101,9 +122,12 @@ impl Qemu {
 					"target/esp/EFI/hermit/hermit-app",
 				)?;
 			}
-			Target::Aarch64Elf | Target::Aarch64BeElf if self.u_boot => {
+			Target::Aarch64Elf if self.u_boot => {
 				sh.create_dir("target/boot")?;
-				sh.copy_file(self.build.dist_object(), "target/boot/hermit-loader")?;
+				sh.copy_file(
+					self.build.dist_binary().unwrap(),
+					"target/boot/hermit-loader",
+				)?;
 				sh.copy_file(
 					self.build.ci_image(self.image.as_deref().unwrap()),
 					"target/boot/hermit-app",
@@ -114,11 +138,11 @@ impl Qemu {
 					"mkimage -f xtask/src/ci/u-boot/boot.its target/boot/boot.scr"
 				)
 				.run()?;
-				cmd!(
-					sh,
-					"mkimage -E -f xtask/src/ci/u-boot/hermit.its target/boot/hermit.fit"
+			}
+			Target::Aarch64BeElf if self.u_boot => {
+				bail!(
+					"aarch64_be-elf cannot be run with U-Boot, since it has no arm64 `Image` for `booti`"
 				)
-				.run()?;
 			}
 			_ => (),
 		}
@@ -126,6 +150,37 @@ impl Qemu {
 		Ok(())
 	}
 
+	fn start_swtpm(&self) -> Result<()> {
+		let sh = crate::sh()?;
+
+		sh.remove_path("target/swtpm")?;
+		sh.create_dir("target/swtpm")?;
+
+		// `--terminate` stops swtpm once QEMU disconnects.
+		cmd!(
+			sh,
+			"swtpm socket --tpm2 --tpmstate dir=target/swtpm --ctrl type=unixio,path=target/swtpm/swtpm.sock --terminate --daemon"
+		)
+		.run()?;
+
+		Ok(())
+	}
+
+	fn tpm_args(&self) -> Vec<String> {
+		if !self.tpm {
+			return vec![];
+		}
+
+		vec![
+			"-chardev".to_string(),
+			"socket,id=chrtpm,path=target/swtpm/swtpm.sock".to_string(),
+			"-tpmdev".to_string(),
+			"emulator,id=tpm0,chardev=chrtpm".to_string(),
+			"-device".to_string(),
+			"tpm-tis,tpmdev=tpm0".to_string(),
+		]
+	}
+
 	fn machine_args(&self) -> Vec<String> {
 		if self.microvm {
 			let frequency = get_frequency();
@@ -142,9 +197,11 @@ impl Qemu {
 			]
 		} else if matches!(
 			self.build.target(),
-			Target::Aarch64Elf | Target::Aarch64BeElf
+			Target::Aarch64Elf | Target::Aarch64BeElf | Target::Aarch64Uefi
 		) {
 			vec!["-machine".to_string(), "virt,gic-version=3".to_string()]
+		} else if self.build.target() == Target::Riscv64Uefi {
+			vec!["-machine".to_string(), "virt".to_string()]
 		} else if self.build.target() == Target::Riscv64Sbi {
 			let opensbi_paths = &[
 				"opensbi/generic/firmware/fw_jump.bin", // Local
@@ -169,7 +226,7 @@ impl Qemu {
 
 	fn cpu_args(&self) -> Vec<String> {
 		match self.build.target() {
-			Target::X86_64Multiboot | Target::X86_64Uefi => {
+			Target::X86_64Multiboot | Target::X86_64Pvh | Target::X86_64Uefi => {
 				let mut cpu_args = if self.accel {
 					if cfg!(target_os = "linux") {
 						vec![
@@ -187,7 +244,7 @@ impl Qemu {
 				cpu_args.push("isa-debug-exit,iobase=0xf4,iosize=0x04".to_string());
 
 				match self.build.target() {
-					Target::X86_64Multiboot => {
+					Target::X86_64Multiboot | Target::X86_64Pvh => {
 						cpu_args.push("-kernel".to_string());
 						cpu_args.push(
 							self.build
@@ -265,7 +322,7 @@ impl Qemu {
 				}
 				cpu_args
 			}
-			Target::X86_64Linux => panic!("unsupported"),
+			Target::X86_64Linux | Target::X86_64Multiboot2 => panic!("unsupported"),
 			Target::Aarch64Elf | Target::Aarch64BeElf => {
 				let mut cpu_args = if self.accel {
 					todo!()
@@ -298,6 +355,84 @@ impl Qemu {
 				}
 				cpu_args
 			}
+			Target::Aarch64Uefi => {
+				use ovmf_prebuilt::{Arch, FileType, Prebuilt, Source};
+
+				let prebuilt = Prebuilt::fetch(Source::LATEST, "target/ovmf")
+					.expect("failed to update prebuilt");
+				let code = prebuilt.get_file(Arch::Aarch64, FileType::Code);
+
+				let mut cpu_args = if self.accel {
+					todo!()
+				} else {
+					vec!["-cpu".to_string(), "cortex-a72".to_string()]
+				};
+				cpu_args.push("-semihosting".to_string());
+				cpu_args.push("-bios".to_string());
+				cpu_args.push(code.display().to_string());
+				if self.esp {
+					cpu_args.push("-drive".to_string());
+					cpu_args.push("format=raw,file=fat:rw:target/esp".to_string());
+				} else {
+					cpu_args.push("-kernel".to_string());
+					cpu_args.push(
+						self.build
+							.dist_object()
+							.into_os_string()
+							.into_string()
+							.unwrap(),
+					);
+					cpu_args.push("-initrd".to_string());
+					cpu_args.push(
+						self.build
+							.ci_image(self.image.as_deref().unwrap())
+							.into_os_string()
+							.into_string()
+							.unwrap(),
+					);
+				}
+				cpu_args
+			}
+			Target::Riscv64Uefi => {
+				use ovmf_prebuilt::{Arch, FileType, Prebuilt, Source};
+
+				let prebuilt = Prebuilt::fetch(Source::LATEST, "target/ovmf")
+					.expect("failed to update prebuilt");
+				let code = prebuilt.get_file(Arch::Riscv64, FileType::Code);
+				let vars = prebuilt.get_file(Arch::Riscv64, FileType::Vars);
+
+				let mut cpu_args = if self.accel {
+					todo!()
+				} else {
+					vec!["-cpu".to_string(), "rv64".to_string()]
+				};
+				cpu_args.push("-drive".to_string());
+				cpu_args.push(format!(
+					"if=pflash,unit=0,format=raw,readonly=on,file={}",
+					code.display()
+				));
+				cpu_args.push("-drive".to_string());
+				cpu_args.push(format!(
+					"if=pflash,unit=1,format=raw,readonly=on,file={}",
+					vars.display()
+				));
+				if self.esp {
+					cpu_args.push("-drive".to_string());
+					cpu_args.push("format=raw,file=fat:rw:target/esp".to_string());
+				} else {
+					cpu_args.push("-kernel".to_string());
+					cpu_args.push(self.efi_application().display().to_string());
+					cpu_args.push("-initrd".to_string());
+					cpu_args.push(
+						self.build
+							.ci_image(self.image.as_deref().unwrap())
+							.into_os_string()
+							.into_string()
+							.unwrap(),
+					);
+				}
+				cpu_args
+			}
 			Target::Riscv64Sbi => {
 				let mut cpu_args = if self.accel {
 					todo!()
@@ -329,7 +464,7 @@ impl Qemu {
 	fn memory(&self) -> usize {
 		let mut memory = 64usize;
 		match self.build.target() {
-			Target::X86_64Uefi => {
+			Target::X86_64Uefi | Target::Aarch64Uefi | Target::Riscv64Uefi => {
 				memory = memory.max(512);
 			}
 			Target::Aarch64Elf | Target::Aarch64BeElf => {
@@ -346,6 +481,16 @@ impl Qemu {
 	fn memory_args(&self) -> [String; 2] {
 		["-m".to_string(), format!("{}M", self.memory())]
 	}
+
+	/// Returns the path of the EFI application.
+	///
+	/// On RISC-V, this is the raw binary with the handwritten PE/COFF header.
+	fn efi_application(&self) -> PathBuf {
+		match self.build.target() {
+			Target::Riscv64Uefi => self.build.dist_binary().unwrap(),
+			_ => self.build.dist_object(),
+		}
+	}
 }
 
 fn get_frequency() -> u64 {
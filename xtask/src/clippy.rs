@@ -15,8 +15,11 @@ impl Clippy {
 		for target in [
 			Target::X86_64Linux,
 			Target::X86_64Multiboot,
+			Target::X86_64Multiboot2,
+			Target::X86_64Pvh,
 			Target::X86_64Uefi,
 			Target::Aarch64Elf,
+			Target::Aarch64Uefi,
 			Target::Riscv64Sbi,
 		] {
 			target.install()?;
@@ -25,6 +28,16 @@ impl Clippy {
 			cmd!(sh, "cargo clippy --target={triple} {feature_flags...}").run()?;
 		}
 
+		// The RISC-V UEFI target is a JSON target specification, which requires nightly.
+		let target = Target::Riscv64Uefi;
+		let cargo_args = target.cargo_args();
+		let feature_flags = target.feature_flags();
+		cmd!(
+			sh,
+			"cargo +nightly clippy {cargo_args...} {feature_flags...}"
+		)
+		.run()?;
+
 		cmd!(sh, "cargo clippy --package xtask").run()?;
 
 		Ok(())
@@ -31,4 +31,13 @@ impl Object {
 		cmd!(sh, "{objcopy} --output-target elf32-i386 {object}").run()?;
 		Ok(())
 	}
+
+	pub fn convert_to_binary(&self, binary: &Self) -> Result<()> {
+		let sh = crate::sh()?;
+		let objcopy = crate::binutil("objcopy")?;
+		let object = self.as_ref();
+		let binary = binary.as_ref();
+		cmd!(sh, "{objcopy} --output-target binary {object} {binary}").run()?;
+		Ok(())
+	}
 }
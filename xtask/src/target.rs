@@ -7,10 +7,14 @@ use xshell::{Shell, cmd};
 pub enum Target {
 	X86_64Linux,
 	X86_64Multiboot,
+	X86_64Multiboot2,
+	X86_64Pvh,
 	X86_64Uefi,
 	Aarch64Elf,
 	Aarch64BeElf,
+	Aarch64Uefi,
 	Riscv64Sbi,
+	Riscv64Uefi,
 }
 
 impl Target {
@@ -22,7 +26,7 @@ impl Target {
 			cmd!(sh, "rustup target add {triple}").run()?;
 		}
 
-		if self == &Self::X86_64Multiboot {
+		if self == &Self::X86_64Multiboot || self.binary_dist_name().is_some() {
 			cmd!(sh, "rustup component add llvm-tools-preview").run()?;
 		}
 
@@ -34,10 +38,14 @@ impl Target {
 		match self {
 			Self::X86_64Linux => "x86_64",
 			Self::X86_64Multiboot => "x86_64",
+			Self::X86_64Multiboot2 => "x86_64",
+			Self::X86_64Pvh => "x86_64",
 			Self::X86_64Uefi => "x86_64",
 			Self::Aarch64Elf => "aarch64",
 			Self::Aarch64BeElf => "aarch64_be",
+			Self::Aarch64Uefi => "aarch64",
 			Self::Riscv64Sbi => "riscv64",
+			Self::Riscv64Uefi => "riscv64",
 		}
 	}
 
@@ -45,16 +53,20 @@ impl Target {
 		match self {
 			Self::X86_64Linux => "x86_64-unknown-none",
 			Self::X86_64Multiboot => "x86_64-unknown-none",
+			Self::X86_64Multiboot2 => "x86_64-unknown-none",
+			Self::X86_64Pvh => "x86_64-unknown-none",
 			Self::X86_64Uefi => "x86_64-unknown-uefi",
 			Self::Aarch64Elf => "aarch64-unknown-none-softfloat",
 			Self::Aarch64BeElf => "aarch64_be-unknown-none-softfloat",
+			Self::Aarch64Uefi => "aarch64-unknown-uefi",
 			Self::Riscv64Sbi => "riscv64imac-unknown-none-elf",
+			Self::Riscv64Uefi => "riscv64gc-unknown-uefi",
 		}
 	}
 
 	pub fn tier(&self) -> u8 {
 		match self {
-			Self::Aarch64BeElf => 3,
+			Self::Aarch64BeElf | Self::Riscv64Uefi => 3,
 			_ => 2,
 		}
 	}
@@ -63,28 +75,44 @@ impl Target {
 		match self {
 			Self::X86_64Linux => &["--target=x86_64-unknown-none"],
 			Self::X86_64Multiboot => &["--target=x86_64-unknown-none"],
+			Self::X86_64Multiboot2 => &["--target=x86_64-unknown-none"],
+			Self::X86_64Pvh => &["--target=x86_64-unknown-none"],
 			Self::X86_64Uefi => &["--target=x86_64-unknown-uefi"],
 			Self::Aarch64Elf => &["--target=aarch64-unknown-none-softfloat"],
 			Self::Aarch64BeElf => &[
 				"--target=aarch64_be-unknown-none-softfloat",
 				"-Zbuild-std=core,alloc,panic_abort",
 			],
+			Self::Aarch64Uefi => &["--target=aarch64-unknown-uefi"],
 			Self::Riscv64Sbi => &["--target=riscv64imac-unknown-none-elf"],
+			// Rust does not have a RISC-V UEFI target, since LLVM cannot emit RISC-V COFF objects.
+			Self::Riscv64Uefi => &[
+				"--target=src/arch/riscv64/riscv64gc-unknown-uefi.json",
+				"-Zjson-target-spec",
+				"-Zbuild-std=core,alloc",
+				"-Zbuild-std-features=compiler-builtins-mem",
+			],
 		}
 	}
 
 	pub fn rustflags(&self) -> &'static [&'static str] {
 		match self {
 			Self::Aarch64Elf | Self::Aarch64BeElf => &[
-				"-Crelocation-model=static",
-				"-Clink-arg=--image-base=0x40400000",
+				"-Crelocation-model=pic",
+				"-Clink-arg=-pie",
+				"-Clink-arg=--no-dynamic-linker",
+				"-Clink-arg=-znotext",
 			],
-			Self::Riscv64Sbi => &[
-				"-Crelocation-model=static",
-				"-Clink-arg=--image-base=0x801ffe00",
-				"-Clink-arg=--section-start=.init=0x80200000",
+			Self::Riscv64Sbi | Self::Riscv64Uefi => &[
+				"-Crelocation-model=pic",
+				"-Clink-arg=-pie",
+				"-Clink-arg=--no-dynamic-linker",
+				"-Clink-arg=-znotext",
 			],
-			Self::X86_64Linux | Self::X86_64Multiboot => &["-Crelocation-model=static"],
+			Self::X86_64Linux
+			| Self::X86_64Multiboot
+			| Self::X86_64Multiboot2
+			| Self::X86_64Pvh => &["-Crelocation-model=static"],
 			_ => &[],
 		}
 	}
@@ -93,6 +121,8 @@ impl Target {
 		match self {
 			Self::X86_64Linux => &["--features=x86_64-linux"],
 			Self::X86_64Multiboot => &["--features=x86_64-multiboot"],
+			Self::X86_64Multiboot2 => &["--features=x86_64-multiboot2"],
+			Self::X86_64Pvh => &["--features=x86_64-pvh"],
 			Self::Aarch64Elf | Self::Aarch64BeElf => &["--features=elf"],
 			Self::Riscv64Sbi => &["--features=sbi"],
 			_ => &[],
@@ -101,7 +131,7 @@ impl Target {
 
 	pub fn image_name(&self) -> &'static str {
 		match self {
-			Self::X86_64Uefi => "hermit-loader.efi",
+			Self::X86_64Uefi | Self::Aarch64Uefi => "hermit-loader.efi",
 			_ => "hermit-loader",
 		}
 	}
@@ -110,19 +140,37 @@ impl Target {
 		match self {
 			Self::X86_64Linux => "hermit-loader-x86_64-linux",
 			Self::X86_64Multiboot => "hermit-loader-x86_64-multiboot",
+			Self::X86_64Multiboot2 => "hermit-loader-x86_64-multiboot2",
+			Self::X86_64Pvh => "hermit-loader-x86_64-pvh",
 			Self::X86_64Uefi => "hermit-loader-x86_64.efi",
 			Self::Aarch64Elf => "hermit-loader-aarch64-elf",
 			Self::Aarch64BeElf => "hermit-loader-aarch64_be-elf",
+			Self::Aarch64Uefi => "hermit-loader-aarch64.efi",
 			Self::Riscv64Sbi => "hermit-loader-riscv64-sbi",
+			Self::Riscv64Uefi => "hermit-loader-riscv64-uefi",
+		}
+	}
+
+	/// Returns the name of the raw binary image, if this target has one.
+	pub fn binary_dist_name(&self) -> Option<&'static str> {
+		match self {
+			Self::Aarch64Elf => Some("hermit-loader-aarch64-image"),
+			Self::Riscv64Sbi => Some("hermit-loader-riscv64-image"),
+			Self::Riscv64Uefi => Some("hermit-loader-riscv64.efi"),
+			_ => None,
 		}
 	}
 
 	#[cfg(feature = "ci")]
 	pub fn qemu(&self) -> &'static str {
 		match self {
-			Self::X86_64Linux | Self::X86_64Multiboot | Self::X86_64Uefi => "x86_64",
-			Self::Aarch64Elf | Self::Aarch64BeElf => "aarch64",
-			Self::Riscv64Sbi => "riscv64",
+			Self::X86_64Linux
+			| Self::X86_64Multiboot
+			| Self::X86_64Multiboot2
+			| Self::X86_64Pvh
+			| Self::X86_64Uefi => "x86_64",
+			Self::Aarch64Elf | Self::Aarch64BeElf | Self::Aarch64Uefi => "aarch64",
+			Self::Riscv64Sbi | Self::Riscv64Uefi => "riscv64",
 		}
 	}
 }
@@ -134,10 +182,14 @@ impl FromStr for Target {
 		match s {
 			"x86_64-linux" => Ok(Self::X86_64Linux),
 			"x86_64-multiboot" => Ok(Self::X86_64Multiboot),
+			"x86_64-multiboot2" => Ok(Self::X86_64Multiboot2),
+			"x86_64-pvh" => Ok(Self::X86_64Pvh),
 			"x86_64-uefi" => Ok(Self::X86_64Uefi),
 			"aarch64-elf" => Ok(Self::Aarch64Elf),
 			"aarch64_be-elf" => Ok(Self::Aarch64BeElf),
+			"aarch64-uefi" => Ok(Self::Aarch64Uefi),
 			"riscv64-sbi" => Ok(Self::Riscv64Sbi),
+			"riscv64-uefi" => Ok(Self::Riscv64Uefi),
 			s => Err(anyhow!("Unsupported target: {s}")),
 		}
 	}